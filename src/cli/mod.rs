@@ -1,5 +1,6 @@
 use std::path::PathBuf;
 
+use chrono::{DateTime, Duration, Utc};
 use clap::{Parser, ValueEnum};
 
 #[derive(Parser)]
@@ -50,12 +51,17 @@ pub enum ConfigCommand {
 pub enum SchemaRegistryCommand {
     /// Download a schema from the schema registry
     Download {
-        #[arg(short, long)]
-        subject: String,
+        #[arg(short, long, required_unless_present = "id")]
+        subject: Option<String>,
 
-        #[arg(short, long)]
+        #[arg(short, long, conflicts_with = "id")]
         version: Option<u32>,
 
+        /// Download the schema with this id, as found in the header of messages,
+        /// listing the subject versions using it
+        #[arg(long, conflicts_with = "subject")]
+        id: Option<u32>,
+
         #[arg(short, long)]
         output: Option<PathBuf>,
     },
@@ -67,8 +73,92 @@ pub enum SchemaRegistryCommand {
 
         #[arg(short, long)]
         schema: PathBuf,
+
+        /// Only check that the schema is compatible, without registering it
+        #[arg(long)]
+        check_only: bool,
+
+        /// Accept the schema when the subject does not exist yet
+        #[arg(long, requires = "check_only")]
+        allow_new_subject: bool,
+    },
+
+    /// Check that a schema is compatible with the versions registered under a subject
+    Check {
+        /// The subject the schema is checked against
+        #[arg(long)]
+        subject: String,
+
+        /// The file containing the schema to be checked
+        #[arg(long)]
+        schema: PathBuf,
+
+        /// Check against every version instead of the latest one. The registry
+        /// still follows the compatibility level of the subject, so every version
+        /// is only checked under a TRANSITIVE level
+        #[arg(long)]
+        all_versions: bool,
+
+        /// Accept the schema when the subject does not exist yet
+        #[arg(long)]
+        allow_new_subject: bool,
+    },
+
+    /// List the subjects with their latest version, schema type and id
+    List {
+        /// Only list subjects starting with this prefix
+        #[arg(long)]
+        prefix: Option<String>,
+
+        /// Only list subjects matching this regular expression
+        #[arg(long)]
+        regex: Option<String>,
+
+        /// Also list soft-deleted subjects
+        #[arg(long)]
+        deleted: bool,
+
+        /// How the subjects are printed
+        #[arg(long, default_value = "text")]
+        format: OutputFormat,
+    },
+
+    /// List the versions registered under a subject
+    Versions {
+        #[arg(short, long)]
+        subject: String,
+
+        /// How the versions are printed
+        #[arg(long, default_value = "text")]
+        format: OutputFormat,
+    },
+
+    /// Delete a subject or one of its versions, soft deleting unless asked otherwise
+    Delete {
+        #[arg(short, long)]
+        subject: String,
+
+        /// Only delete this version (defaults to every version)
+        #[arg(short, long)]
+        version: Option<u32>,
+
+        /// Permanently delete the versions, soft deleting them first when needed
+        #[arg(long)]
+        permanent: bool,
+
+        /// Only show the versions that would be deleted
+        #[arg(long)]
+        dry_run: bool,
+
+        /// Do not ask for confirmation
+        #[arg(short, long)]
+        yes: bool,
     },
 
+    /// Show and change the compatibility of subjects or the global one
+    #[command(subcommand)]
+    Compatibility(CompatibilityCommand),
+
     /// Compare a schema with the one in the schema registry
     Diff {
         /// The subject of the schema to be compared
@@ -85,8 +175,47 @@ pub enum SchemaRegistryCommand {
     },
 }
 
+#[derive(Parser)]
+pub enum CompatibilityCommand {
+    /// Show the compatibility of a subject, or the global one
+    Get {
+        /// The subject whose compatibility is shown (defaults to the global one)
+        #[arg(short, long)]
+        subject: Option<String>,
+
+        /// How the compatibility is printed
+        #[arg(long, default_value = "text")]
+        format: OutputFormat,
+    },
+
+    /// Change the compatibility of a subject, or the global one
+    Set {
+        /// The subject whose compatibility is changed (defaults to the global one)
+        #[arg(short, long)]
+        subject: Option<String>,
+
+        /// The new compatibility
+        #[arg(required_unless_present = "inherit", ignore_case = true)]
+        level: Option<CompatibilityLevel>,
+
+        /// Remove the compatibility of the subject so it inherits the global one
+        #[arg(long, requires = "subject", conflicts_with = "level")]
+        inherit: bool,
+    },
+}
+
 #[derive(Parser)]
 pub enum KafkaCommand {
+    #[command(flatten)]
+    Context(KafkaContextCommand),
+
+    /// Copy messages from a topic to another, possibly in another context
+    Copy(CopyArgs),
+}
+
+/// The kafka commands run against the cluster of the context given with `--context`.
+#[derive(Parser)]
+pub enum KafkaContextCommand {
     /// Listen to kafka topic and print or save the messages
     Consume {
         /// The topic to be consumed
@@ -96,6 +225,90 @@ pub enum KafkaCommand {
         /// Indicates the encoding of the messages
         #[arg(short, long, default_value = "raw")]
         decode: CodecKind,
+
+        /// How messages decoded with the raw codec are rendered
+        #[arg(long, default_value = "json")]
+        raw_format: RawFormat,
+
+        /// Indicates the encoding of the message keys (raw keys are printed as text)
+        #[arg(long, default_value = "raw")]
+        key_decode: CodecKind,
+
+        /// Consume as part of this consumer group (by default partitions are only
+        /// peeked at, without joining any group). Stopping at the end, an offset or
+        /// a timestamp requires a start position or explicit partitions
+        #[arg(short, long)]
+        group: Option<String>,
+
+        /// Commit the consumed offsets to the consumer group
+        #[arg(long, requires = "group")]
+        commit: bool,
+
+        /// Read the assigned partitions from the beginning
+        #[arg(long, conflicts_with_all = ["from_end", "offset", "from_timestamp"])]
+        from_beginning: bool,
+
+        /// Read only messages produced after the consumer starts
+        #[arg(long, conflicts_with_all = ["offset", "from_timestamp"])]
+        from_end: bool,
+
+        /// Start at this offset, negative values count back from the end of each partition
+        #[arg(long, allow_negative_numbers = true, conflicts_with = "from_timestamp")]
+        offset: Option<i64>,
+
+        /// Start at the first message at or after this time, as milliseconds since the
+        /// epoch, RFC 3339 or relative to now (e.g. -1h, -30m, -2d)
+        #[arg(long, value_parser = parse_timestamp, allow_hyphen_values = true)]
+        from_timestamp: Option<i64>,
+
+        /// Only read these partitions (may be repeated), defaults to every partition
+        #[arg(short, long)]
+        partition: Vec<i32>,
+
+        /// Exit after printing this many messages
+        #[arg(short = 'n', long)]
+        max_messages: Option<usize>,
+
+        /// Exit once every partition reaches the end offset it had when the consumer started
+        #[arg(long)]
+        until_end: bool,
+
+        /// Stop reading each partition after the message at this offset, or at the end
+        /// it had when the consumer started if it doesn't reach the offset
+        #[arg(long)]
+        until_offset: Option<i64>,
+
+        /// Stop reading each partition at the first message after this time, as
+        /// milliseconds since the epoch, RFC 3339 or relative to now (e.g. -1h)
+        #[arg(long, value_parser = parse_timestamp, allow_hyphen_values = true)]
+        until_timestamp: Option<i64>,
+    },
+
+    /// Print the message at an offset of a partition
+    Get {
+        /// The topic of the message
+        #[arg(short, long)]
+        topic: String,
+
+        /// The partition of the message
+        #[arg(short, long)]
+        partition: i32,
+
+        /// The offset of the message
+        #[arg(short, long)]
+        offset: i64,
+
+        /// Indicates the encoding of the message
+        #[arg(short, long, default_value = "raw")]
+        decode: CodecKind,
+
+        /// How a message decoded with the raw codec is rendered
+        #[arg(long, default_value = "json")]
+        raw_format: RawFormat,
+
+        /// Indicates the encoding of the message key (raw keys are printed as text)
+        #[arg(long, default_value = "raw")]
+        key_decode: CodecKind,
     },
 
     /// Send messages to a kafka topic
@@ -116,9 +329,540 @@ pub enum KafkaCommand {
         #[arg(long, conflicts_with = "message")]
         payload: Option<PathBuf>,
 
-        /// The key of the message to be sent (if not specified will be empty)
+        /// A JSON Lines file with one message per line, shaped like the payload file
+        /// ("-" reads from stdin)
+        #[arg(long, conflicts_with_all = ["message", "payload"])]
+        jsonl: Option<PathBuf>,
+
+        /// Read the payload file or JSON Lines as objects shaped like the consumed
+        /// messages (key, value, headers, partition and timestamp) instead of bare
+        /// values. A missing or null value produces a tombstone
+        #[arg(long, conflicts_with = "message")]
+        envelope: bool,
+
+        /// Maximum number of messages waiting to be delivered when producing JSON Lines
+        #[arg(long, default_value_t = 100)]
+        max_in_flight: usize,
+
+        /// Fail messages not delivered within this many milliseconds
+        #[arg(long)]
+        delivery_timeout: Option<u64>,
+
+        /// How the delivery reports are printed
+        #[arg(long, default_value = "text")]
+        format: OutputFormat,
+
+        /// The key of the message to be sent (if not specified the message has no key)
         #[arg(short, long)]
         key: Option<String>,
+
+        /// Indicates the encoding of the key, non raw keys must be valid JSON
+        #[arg(long, default_value = "raw")]
+        key_encode: CodecKind,
+
+        /// A header to be sent with the message, as key=value (may be repeated)
+        #[arg(short = 'H', long = "header", value_parser = parse_key_value)]
+        headers: Vec<(String, String)>,
+
+        /// The partition to send the message to (defaults to the partitioner choice)
+        #[arg(short, long)]
+        partition: Option<i32>,
+
+        /// The message timestamp, as milliseconds since the epoch, RFC 3339 or
+        /// relative to now (e.g. -1h)
+        #[arg(long, value_parser = parse_timestamp, allow_hyphen_values = true)]
+        timestamp: Option<i64>,
+
+        /// The protobuf message type to encode, when the schema declares several
+        /// (defaults to the first message in the schema)
+        #[arg(long)]
+        message_type: Option<String>,
+
+        /// The schema version used to encode protobuf messages (defaults to the latest)
+        #[arg(long)]
+        schema_version: Option<u32>,
+    },
+
+    /// Inspect and replay dead-letter queues
+    #[command(subcommand)]
+    Dlq(DlqCommand),
+
+    /// List, describe, inspect the offsets of, create and delete topics
+    #[command(subcommand)]
+    Topic(TopicCommand),
+
+    /// Describe and alter topic and broker configurations
+    #[command(subcommand)]
+    Config(KafkaConfigCommand),
+
+    /// Show the brokers and partitions of the cluster, failing when problems are detected
+    Cluster {
+        /// How the report is printed
+        #[arg(long, default_value = "text")]
+        format: OutputFormat,
+    },
+
+    /// List, create and delete ACLs
+    #[command(subcommand)]
+    Acl(AclCommand),
+
+    /// List, describe, reset and delete consumer groups and report their lag
+    #[command(subcommand)]
+    Group(GroupCommand),
+}
+
+#[derive(Parser)]
+pub enum AclCommand {
+    /// List the ACLs matching the filters
+    List {
+        #[command(flatten)]
+        filter: AclFilterArgs,
+
+        /// How the ACLs are printed
+        #[arg(long, default_value = "text")]
+        format: OutputFormat,
+    },
+
+    /// Grant or deny operations on a resource to a principal
+    Create {
+        /// The type of the resource
+        #[arg(long)]
+        resource_type: AclResourceType,
+
+        /// The name of the resource (kafka-cluster for the cluster)
+        #[arg(short, long)]
+        resource: String,
+
+        /// How the resource name is matched
+        #[arg(long, default_value = "literal")]
+        pattern_type: AclPatternType,
+
+        /// The principal, e.g. User:my-service
+        #[arg(long)]
+        principal: String,
+
+        /// The host the principal connects from
+        #[arg(long, default_value = "*")]
+        host: String,
+
+        /// The operation granted or denied (may be repeated)
+        #[arg(short, long, required = true)]
+        operation: Vec<AclOperation>,
+
+        /// Whether the operations are allowed or denied
+        #[arg(long, default_value = "allow")]
+        permission: AclPermission,
+    },
+
+    /// Delete the ACLs matching the filters
+    Delete {
+        #[command(flatten)]
+        filter: AclFilterArgs,
+
+        /// Allow deleting without a resource, principal or operation filter, which
+        /// can match every ACL of the cluster
+        #[arg(long)]
+        all: bool,
+
+        /// Do not ask for confirmation
+        #[arg(short, long)]
+        yes: bool,
+    },
+}
+
+/// Filters matching ACLs, any ACL matches when none is given
+#[derive(Parser)]
+pub struct AclFilterArgs {
+    /// The type of the resource
+    #[arg(long, default_value = "any")]
+    pub resource_type: AclResourceType,
+
+    /// The name of the resource
+    #[arg(short, long)]
+    pub resource: Option<String>,
+
+    /// How the resource name is matched, match also includes prefixed and wildcard ACLs
+    #[arg(long, default_value = "any")]
+    pub pattern_type: AclPatternType,
+
+    /// The principal, e.g. User:my-service
+    #[arg(long)]
+    pub principal: Option<String>,
+
+    /// The host the principal connects from
+    #[arg(long)]
+    pub host: Option<String>,
+
+    /// The operation
+    #[arg(short, long, default_value = "any")]
+    pub operation: AclOperation,
+
+    /// Whether the operations are allowed or denied
+    #[arg(long, default_value = "any")]
+    pub permission: AclPermission,
+}
+
+#[derive(Parser)]
+pub enum GroupCommand {
+    /// List the consumer groups with their state and member count
+    List {
+        /// How the groups are printed
+        #[arg(long, default_value = "text")]
+        format: OutputFormat,
+    },
+
+    /// Show the state of a group and the partitions assigned to each member
+    Describe {
+        /// The group to be described
+        #[arg(short, long)]
+        group: String,
+
+        /// How the group is printed
+        #[arg(long, default_value = "text")]
+        format: OutputFormat,
+    },
+
+    /// Show the committed offset, high watermark and lag of each partition
+    Lag {
+        /// The group whose lag is reported
+        #[arg(short, long)]
+        group: String,
+
+        /// Only report this topic (defaults to the topics the group has committed to)
+        #[arg(short, long)]
+        topic: Option<String>,
+
+        /// How the lag is printed
+        #[arg(long, default_value = "text")]
+        format: OutputFormat,
+    },
+
+    /// Move the committed offsets of a group, previewing the changes unless executed
+    #[command(group(
+        clap::ArgGroup::new("reset")
+            .required(true)
+            .args(["to_earliest", "to_latest", "to_offset", "shift_by", "to_timestamp", "from_file"]),
+    ))]
+    ResetOffsets {
+        /// The group whose offsets are reset, it must not have active members
+        #[arg(short, long)]
+        group: String,
+
+        /// The topic whose offsets are reset (optional with --from-file)
+        #[arg(short, long, required_unless_present = "from_file")]
+        topic: Option<String>,
+
+        /// Only reset these partitions (may be repeated), defaults to every partition
+        #[arg(short, long)]
+        partition: Vec<i32>,
+
+        /// Move to the earliest available offset
+        #[arg(long)]
+        to_earliest: bool,
+
+        /// Move to the end of each partition
+        #[arg(long)]
+        to_latest: bool,
+
+        /// Move to this offset
+        #[arg(long)]
+        to_offset: Option<i64>,
+
+        /// Move the committed offsets by this many messages, negative values move back
+        #[arg(long, allow_negative_numbers = true)]
+        shift_by: Option<i64>,
+
+        /// Move to the first message at or after this time, as milliseconds since the
+        /// epoch, RFC 3339 or relative to now (e.g. -1h)
+        #[arg(long, value_parser = parse_timestamp, allow_hyphen_values = true)]
+        to_timestamp: Option<i64>,
+
+        /// Move to the offsets listed in a JSON file or a CSV file of
+        /// topic,partition,offset lines
+        #[arg(long)]
+        from_file: Option<PathBuf>,
+
+        /// Commit the new offsets, otherwise they are only previewed
+        #[arg(long)]
+        execute: bool,
+
+        /// How the offset changes are printed
+        #[arg(long, default_value = "text")]
+        format: OutputFormat,
+    },
+
+    /// Delete consumer groups and their committed offsets
+    Delete {
+        /// The groups to be deleted (may be repeated)
+        #[arg(short, long, required = true)]
+        group: Vec<String>,
+
+        /// Do not ask for confirmation
+        #[arg(short, long)]
+        yes: bool,
+    },
+}
+
+#[derive(Parser)]
+pub struct CopyArgs {
+    /// The context the messages are read from
+    #[arg(long)]
+    pub from_context: String,
+
+    /// The topic the messages are read from
+    #[arg(long)]
+    pub from_topic: String,
+
+    /// The context the messages are written to
+    #[arg(long)]
+    pub to_context: String,
+
+    /// The topic the messages are written to (defaults to the source topic)
+    #[arg(long)]
+    pub to_topic: Option<String>,
+
+    /// Start at this offset, negative values count back from the end of each partition
+    /// (defaults to the beginning of the partitions)
+    #[arg(long, allow_negative_numbers = true, conflicts_with = "from_timestamp")]
+    pub offset: Option<i64>,
+
+    /// Start at the first message at or after this time, as milliseconds since the
+    /// epoch, RFC 3339 or relative to now (e.g. -1h)
+    #[arg(long, value_parser = parse_timestamp, allow_hyphen_values = true)]
+    pub from_timestamp: Option<i64>,
+
+    /// Stop copying each partition after the message at this offset
+    #[arg(long)]
+    pub until_offset: Option<i64>,
+
+    /// Stop copying each partition at the first message after this time, as
+    /// milliseconds since the epoch, RFC 3339 or relative to now (e.g. -1h)
+    #[arg(long, value_parser = parse_timestamp, allow_hyphen_values = true)]
+    pub until_timestamp: Option<i64>,
+
+    /// Only copy these partitions (may be repeated), defaults to every partition
+    #[arg(short, long)]
+    pub partition: Vec<i32>,
+
+    /// Exit after copying this many messages
+    #[arg(short = 'n', long)]
+    pub max_messages: Option<usize>,
+
+    /// Write each message to the partition it was read from
+    #[arg(long)]
+    pub preserve_partition: bool,
+
+    /// Indicates the encoding of the source messages
+    #[arg(short, long, default_value = "raw")]
+    pub decode: CodecKind,
+
+    /// Indicates the encoding of the copied messages (defaults to the source encoding)
+    #[arg(short, long)]
+    pub encode: Option<CodecKind>,
+
+    /// Indicates the encoding of the source keys
+    #[arg(long, default_value = "raw")]
+    pub key_decode: CodecKind,
+
+    /// Indicates the encoding of the copied keys (defaults to the source encoding)
+    #[arg(long)]
+    pub key_encode: Option<CodecKind>,
+
+    /// The protobuf message type to encode, when the destination schema declares
+    /// several (defaults to the first message in the schema)
+    #[arg(long)]
+    pub message_type: Option<String>,
+
+    /// The destination schema version used to encode protobuf messages (defaults
+    /// to the latest)
+    #[arg(long)]
+    pub schema_version: Option<u32>,
+
+    /// Maximum number of messages waiting to be delivered
+    #[arg(long, default_value_t = 100)]
+    pub max_in_flight: usize,
+}
+
+#[derive(Parser)]
+pub enum DlqCommand {
+    /// Summarize the errors of a dead-letter queue and republish its messages to
+    /// their original topic
+    Replay {
+        /// The dead-letter queue topic
+        #[arg(short, long)]
+        topic: String,
+
+        /// The header holding the topic each message was dead-lettered from
+        #[arg(long, required_unless_present = "to_topic")]
+        topic_header: Option<String>,
+
+        /// Republish every message to this topic
+        #[arg(long)]
+        to_topic: Option<String>,
+
+        /// Only replay messages whose header matches, as key=value, key!=value or
+        /// key~text for values containing the text (may be repeated)
+        #[arg(long = "filter", value_parser = parse_header_filter)]
+        filters: Vec<HeaderFilter>,
+
+        /// A header the summary groups the messages by (may be repeated), defaults
+        /// to the non numeric headers whose name mentions an error or exception
+        #[arg(long = "summarize")]
+        summary_headers: Vec<String>,
+
+        /// The header counting how many times a message was replayed
+        #[arg(long, default_value = "ktools-replay-count")]
+        replay_header: String,
+
+        /// Skip messages already replayed this many times
+        #[arg(long)]
+        max_replays: Option<u32>,
+
+        /// Only read these partitions (may be repeated), defaults to every partition
+        #[arg(short, long)]
+        partition: Vec<i32>,
+
+        /// Read at most this many messages
+        #[arg(short = 'n', long)]
+        max_messages: Option<usize>,
+
+        /// Maximum number of messages waiting to be delivered
+        #[arg(long, default_value_t = 100)]
+        max_in_flight: usize,
+
+        /// Only show the summary and the selected messages, without replaying them
+        #[arg(long)]
+        dry_run: bool,
+
+        /// Do not ask for confirmation
+        #[arg(short, long)]
+        yes: bool,
+    },
+}
+
+#[derive(Parser)]
+pub enum KafkaConfigCommand {
+    /// Show every configuration entry along with where its value comes from
+    Describe {
+        /// The topic whose configuration is described
+        #[arg(
+            short,
+            long,
+            required_unless_present = "broker",
+            conflicts_with = "broker"
+        )]
+        topic: Option<String>,
+
+        /// The id of the broker whose configuration is described
+        #[arg(short, long)]
+        broker: Option<i32>,
+
+        /// How the configuration is printed
+        #[arg(long, default_value = "text")]
+        format: OutputFormat,
+    },
+
+    /// Set or reset configuration entries, previewing the changes before applying them
+    Alter {
+        /// The topic whose configuration is altered
+        #[arg(
+            short,
+            long,
+            required_unless_present = "broker",
+            conflicts_with = "broker"
+        )]
+        topic: Option<String>,
+
+        /// The id of the broker whose configuration is altered
+        #[arg(short, long)]
+        broker: Option<i32>,
+
+        /// An entry to be set, as key=value (may be repeated)
+        #[arg(long, value_parser = parse_key_value)]
+        set: Vec<(String, String)>,
+
+        /// An entry to be reset to its default (may be repeated)
+        #[arg(long)]
+        reset: Vec<String>,
+
+        /// Only show the changes, without applying them
+        #[arg(long)]
+        dry_run: bool,
+
+        /// Do not ask for confirmation
+        #[arg(short, long)]
+        yes: bool,
+    },
+}
+
+#[derive(Parser)]
+pub enum TopicCommand {
+    /// List the topics with their partition count and replication factor
+    List {
+        /// Only list topics whose name contains this text
+        #[arg(short, long)]
+        filter: Option<String>,
+
+        /// How the topics are printed
+        #[arg(long, default_value = "text")]
+        format: OutputFormat,
+    },
+
+    /// Show the leader, replicas, in-sync replicas and watermarks of each partition
+    Describe {
+        /// The topic to be described
+        #[arg(short, long)]
+        topic: String,
+
+        /// How the partitions are printed
+        #[arg(long, default_value = "text")]
+        format: OutputFormat,
+    },
+
+    /// Show the watermarks and approximate message count of each partition
+    Offsets {
+        /// The topic to be inspected
+        #[arg(short, long)]
+        topic: String,
+
+        /// Also show the offset of the first message at or after this time, as
+        /// milliseconds since the epoch, RFC 3339 or relative to now (e.g. -1h)
+        #[arg(long, value_parser = parse_timestamp, allow_hyphen_values = true)]
+        at: Option<i64>,
+
+        /// How the offsets are printed
+        #[arg(long, default_value = "text")]
+        format: OutputFormat,
+    },
+
+    /// Create a topic
+    Create {
+        /// The topic to be created
+        #[arg(short, long)]
+        topic: String,
+
+        /// The number of partitions
+        #[arg(short, long, default_value_t = 1)]
+        partitions: i32,
+
+        /// The number of replicas of each partition
+        #[arg(short, long, default_value_t = 1)]
+        replication_factor: i32,
+
+        /// A topic configuration, as key=value (may be repeated)
+        #[arg(long = "config", value_parser = parse_key_value)]
+        configs: Vec<(String, String)>,
+    },
+
+    /// Delete topics
+    Delete {
+        /// The topics to be deleted (may be repeated)
+        #[arg(short, long, required = true)]
+        topic: Vec<String>,
+
+        /// Do not ask for confirmation
+        #[arg(short, long)]
+        yes: bool,
     },
 }
 
@@ -130,3 +874,285 @@ pub enum CodecKind {
     #[default]
     Raw,
 }
+
+/// Which earlier schema versions a new version must be compatible with
+#[derive(Debug, Clone, Copy, ValueEnum, PartialEq, Eq)]
+pub enum CompatibilityLevel {
+    /// Consumers using the new schema can read data written with the latest one
+    Backward,
+    /// Consumers using the new schema can read data written with any earlier one
+    BackwardTransitive,
+    /// Consumers using the latest schema can read data written with the new one
+    Forward,
+    /// Consumers using any earlier schema can read data written with the new one
+    ForwardTransitive,
+    /// Both backward and forward compatible with the latest schema
+    Full,
+    /// Both backward and forward compatible with every earlier schema
+    FullTransitive,
+    /// No compatibility checks
+    None,
+}
+
+/// How command results are printed
+#[derive(Default, Clone, Copy, ValueEnum, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// Human readable text
+    #[default]
+    Text,
+    /// JSON, one document per result
+    Json,
+}
+
+/// How values decoded with the raw codec are printed
+#[derive(Default, Clone, Copy, ValueEnum, PartialEq, Eq)]
+pub enum RawFormat {
+    /// UTF-8 text, invalid sequences are replaced
+    Text,
+    /// Parsed JSON when the value is valid JSON, text otherwise
+    #[default]
+    Json,
+    /// Lowercase hexadecimal string
+    Hex,
+    /// Standard base64 string
+    Base64,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum, PartialEq, Eq)]
+pub enum AclResourceType {
+    Any,
+    Topic,
+    Group,
+    Cluster,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum, PartialEq, Eq)]
+pub enum AclPatternType {
+    /// Any pattern type (filters only)
+    Any,
+    /// ACLs applying to the resource name, whatever their pattern type (filters only)
+    Match,
+    /// The exact resource name, or * for every resource
+    Literal,
+    /// Every resource whose name starts with the given prefix
+    Prefixed,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum, PartialEq, Eq)]
+pub enum AclOperation {
+    Any,
+    All,
+    Read,
+    Write,
+    Create,
+    Delete,
+    Alter,
+    Describe,
+    ClusterAction,
+    DescribeConfigs,
+    AlterConfigs,
+    IdempotentWrite,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum, PartialEq, Eq)]
+pub enum AclPermission {
+    /// Any permission (filters only)
+    Any,
+    Allow,
+    Deny,
+}
+
+/// A condition on the value of a message header
+#[derive(Debug, Clone)]
+pub struct HeaderFilter {
+    pub key: String,
+    pub operator: HeaderOperator,
+    pub value: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HeaderOperator {
+    Equals,
+    NotEquals,
+    Contains,
+}
+
+/// Parse a timestamp given either as milliseconds since the epoch, in RFC 3339
+/// or relative to now, returning milliseconds since the epoch.
+pub fn parse_timestamp(value: &str) -> Result<i64, String> {
+    if value.bytes().all(|c| c.is_ascii_digit()) {
+        if let Ok(millis) = value.parse() {
+            return Ok(millis);
+        }
+    }
+
+    if let Ok(timestamp) = DateTime::parse_from_rfc3339(value) {
+        return Ok(timestamp.timestamp_millis());
+    }
+
+    let (sign, relative) = match value.strip_prefix('-') {
+        Some(relative) => (-1, relative),
+        None => (1, value.strip_prefix('+').unwrap_or(value)),
+    };
+
+    let split = relative
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(relative.len());
+    let (amount, unit) = relative.split_at(split);
+
+    let amount: i64 = amount.parse().map_err(|_| {
+        format!(
+            "Invalid timestamp {:?}, expected RFC 3339 or e.g. -1h",
+            value
+        )
+    })?;
+
+    let unit_millis: i64 = match unit {
+        "ms" => 1,
+        "s" => 1_000,
+        "m" => 60_000,
+        "h" => 3_600_000,
+        "d" => 86_400_000,
+        "w" => 604_800_000,
+        _ => {
+            return Err(format!(
+                "Invalid time unit {:?}, expected ms, s, m, h, d or w",
+                unit
+            ))
+        }
+    };
+
+    amount
+        .checked_mul(unit_millis * sign)
+        .and_then(|millis| Utc::now().checked_add_signed(Duration::milliseconds(millis)))
+        .map(|timestamp| timestamp.timestamp_millis())
+        .ok_or_else(|| format!("Timestamp {:?} is out of range", value))
+}
+
+/// Parse a `key=value` pair.
+pub fn parse_key_value(value: &str) -> Result<(String, String), String> {
+    let (key, value) = value
+        .split_once('=')
+        .ok_or_else(|| format!("Invalid value {:?}, expected key=value", value))?;
+
+    Ok((key.to_string(), value.to_string()))
+}
+
+/// Parse a `key=value`, `key!=value` or `key~text` header filter.
+pub fn parse_header_filter(value: &str) -> Result<HeaderFilter, String> {
+    let split = value
+        .find(['=', '~'])
+        .ok_or_else(|| format!("Invalid filter {:?}, expected key=value", value))?;
+
+    let (key, rest) = value.split_at(split);
+
+    let (key, operator) = match (rest.starts_with('~'), key.strip_suffix('!')) {
+        (true, _) => (key, HeaderOperator::Contains),
+        (false, Some(key)) => (key, HeaderOperator::NotEquals),
+        (false, None) => (key, HeaderOperator::Equals),
+    };
+
+    Ok(HeaderFilter {
+        key: key.to_string(),
+        operator,
+        value: rest[1..].to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn timestamps_in_milliseconds() {
+        assert_eq!(parse_timestamp("0"), Ok(0));
+        assert_eq!(parse_timestamp("1700000000000"), Ok(1_700_000_000_000));
+    }
+
+    #[test]
+    fn timestamps_in_rfc_3339() {
+        assert_eq!(
+            parse_timestamp("2023-11-14T22:13:20Z"),
+            Ok(1_700_000_000_000)
+        );
+        assert_eq!(
+            parse_timestamp("2023-11-14T23:13:20.5+01:00"),
+            Ok(1_700_000_000_500)
+        );
+    }
+
+    #[test]
+    fn timestamps_relative_to_now() {
+        let close_to = |value: &str, offset: i64| {
+            let expected = Utc::now().timestamp_millis() + offset;
+            let parsed = parse_timestamp(value).unwrap();
+            (parsed - expected).abs() < 60_000
+        };
+
+        assert!(close_to("-1h", -3_600_000));
+        assert!(close_to("-30m", -1_800_000));
+        assert!(close_to("-2d", -172_800_000));
+        assert!(close_to("-1w", -604_800_000));
+        assert!(close_to("+90s", 90_000));
+        assert!(close_to("500ms", 500));
+    }
+
+    #[test]
+    fn negative_and_overflowing_timestamps_are_rejected() {
+        // Negative values are relative and need a unit
+        assert!(parse_timestamp("-1700000000000").is_err());
+        // Too large for milliseconds
+        assert!(parse_timestamp("99999999999999999999").is_err());
+        // Too far from now once converted to milliseconds
+        assert!(parse_timestamp("-9223372036854775807w").is_err());
+        assert!(parse_timestamp("+9000000000000000ms").is_err());
+    }
+
+    #[test]
+    fn invalid_timestamps_are_rejected() {
+        for value in ["", "-", "yesterday", "-1x", "1.5h", "2023-11-14", "-h"] {
+            assert!(parse_timestamp(value).is_err(), "{:?} was accepted", value);
+        }
+    }
+
+    #[test]
+    fn header_filters() {
+        let parse = |value: &str| {
+            parse_header_filter(value).map(|filter| (filter.key, filter.operator, filter.value))
+        };
+        let filter =
+            |key: &str, operator, value: &str| Ok((key.to_string(), operator, value.to_string()));
+
+        assert_eq!(
+            parse("error=timeout"),
+            filter("error", HeaderOperator::Equals, "timeout")
+        );
+        assert_eq!(
+            parse("error!=timeout"),
+            filter("error", HeaderOperator::NotEquals, "timeout")
+        );
+        assert_eq!(
+            parse("error~time"),
+            filter("error", HeaderOperator::Contains, "time")
+        );
+        // Only the first operator splits the filter
+        assert_eq!(
+            parse("query=a=b"),
+            filter("query", HeaderOperator::Equals, "a=b")
+        );
+        assert_eq!(
+            parse("error~a!=b"),
+            filter("error", HeaderOperator::Contains, "a!=b")
+        );
+        assert_eq!(
+            parse("source="),
+            filter("source", HeaderOperator::Equals, "")
+        );
+    }
+
+    #[test]
+    fn invalid_header_filters_are_rejected() {
+        assert!(parse_header_filter("error").is_err());
+        assert!(parse_header_filter("").is_err());
+    }
+}
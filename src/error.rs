@@ -0,0 +1,49 @@
+use std::fmt;
+
+/// Exit codes reported for specific failures, so scripts can tell them apart.
+///
+/// Any other error exits with 1, while 2 is used by clap for invalid arguments.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExitCode {
+    /// The message could not be read or encoded
+    InvalidMessage = 3,
+    /// The message was rejected or could not be delivered
+    DeliveryFailed = 4,
+    /// The message was not delivered within the delivery timeout
+    DeliveryTimedOut = 5,
+    /// The cluster has offline or under-replicated partitions, or is imbalanced
+    ClusterUnhealthy = 6,
+    /// The schema is not compatible with the versions registered under its subject
+    IncompatibleSchema = 7,
+}
+
+/// An error that makes ktools exit with a specific [`ExitCode`].
+#[derive(Debug)]
+pub struct ExitError {
+    pub code: ExitCode,
+    pub message: String,
+}
+
+impl ExitError {
+    pub fn new(code: ExitCode, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for ExitError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for ExitError {}
+
+/// The process exit code for an error returned by ktools.
+pub fn exit_code(err: &anyhow::Error) -> i32 {
+    err.chain()
+        .find_map(|cause| cause.downcast_ref::<ExitError>())
+        .map_or(1, |err| err.code as i32)
+}
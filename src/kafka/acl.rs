@@ -0,0 +1,437 @@
+//! ACL administration, which the Rust client does not wrap, through the
+//! librdkafka admin API.
+
+use std::ffi::{c_char, CStr, CString};
+use std::ptr;
+
+use anyhow::{bail, Context};
+use rdkafka::bindings::{self as rdsys, rd_kafka_AclBinding_t};
+use rdkafka::consumer::Consumer;
+use rdkafka::types::RDKafkaRespErr;
+use serde::Serialize;
+
+use crate::cli::{AclOperation, AclPatternType, AclPermission, AclResourceType, OutputFormat};
+use crate::kafka::client::{KafkaClient, REQUEST_TIMEOUT};
+use crate::terminal;
+
+/// Matches ACL bindings, unset fields match anything.
+#[derive(Debug)]
+pub struct AclFilter {
+    pub resource_type: AclResourceType,
+    pub resource: Option<String>,
+    pub pattern_type: AclPatternType,
+    pub principal: Option<String>,
+    pub host: Option<String>,
+    pub operation: AclOperation,
+    pub permission: AclPermission,
+}
+
+impl AclFilter {
+    /// Whether the filter narrows the ACLs down by resource, principal or
+    /// operation, rather than only by type, pattern, host or permission.
+    pub fn is_narrowed(&self) -> bool {
+        self.resource.is_some() || self.principal.is_some() || self.operation != AclOperation::Any
+    }
+}
+
+#[derive(Debug)]
+pub struct NewAcl {
+    pub resource_type: AclResourceType,
+    pub resource: String,
+    pub pattern_type: AclPatternType,
+    pub principal: String,
+    pub host: String,
+    pub operations: Vec<AclOperation>,
+    pub permission: AclPermission,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AclEntry {
+    pub resource_type: String,
+    pub resource: String,
+    pub pattern_type: String,
+    pub principal: String,
+    pub host: String,
+    pub operation: String,
+    pub permission: String,
+}
+
+impl KafkaClient {
+    pub fn list_acls(&self, filter: &AclFilter, format: OutputFormat) -> anyhow::Result<()> {
+        let acls = self.describe_acls(filter)?;
+        print_acls(&acls, format)
+    }
+
+    pub fn create_acls(&self, acl: &NewAcl) -> anyhow::Result<()> {
+        let bindings = acl
+            .operations
+            .iter()
+            .map(|&operation| NativeAcl::binding(acl, operation))
+            .collect::<anyhow::Result<Vec<NativeAcl>>>()?;
+
+        let mut pointers = bindings.iter().map(|acl| acl.0).collect::<Vec<_>>();
+
+        let event = self.admin_request(|rk, queue| unsafe {
+            rdsys::rd_kafka_CreateAcls(
+                rk,
+                pointers.as_mut_ptr(),
+                pointers.len(),
+                ptr::null(),
+                queue,
+            )
+        })?;
+
+        let mut count = 0;
+        let results = unsafe {
+            let result = rdsys::rd_kafka_event_CreateAcls_result(event.0);
+            rdsys::rd_kafka_CreateAcls_result_acls(result, &mut count)
+        };
+
+        let mut failed = false;
+
+        for (idx, operation) in acl.operations.iter().enumerate().take(count) {
+            let error = unsafe { rdsys::rd_kafka_acl_result_error(*results.add(idx)) };
+
+            match error_string(error) {
+                None => println!(
+                    "Created ACL: {} {:?} on {:?} {}",
+                    acl.principal, operation, acl.resource_type, acl.resource
+                ),
+                Some(err) => {
+                    eprintln!("Could not create ACL for {:?}: {}", operation, err);
+                    failed = true;
+                }
+            }
+        }
+
+        if failed {
+            bail!("Some ACLs could not be created");
+        }
+
+        Ok(())
+    }
+
+    pub fn delete_acls(&self, filter: &AclFilter) -> anyhow::Result<()> {
+        let binding = NativeAcl::filter(filter)?;
+        let mut pointers = [binding.0];
+
+        let event = self.admin_request(|rk, queue| unsafe {
+            rdsys::rd_kafka_DeleteAcls(rk, pointers.as_mut_ptr(), 1, ptr::null(), queue)
+        })?;
+
+        let mut count = 0;
+        let responses = unsafe {
+            let result = rdsys::rd_kafka_event_DeleteAcls_result(event.0);
+            rdsys::rd_kafka_DeleteAcls_result_responses(result, &mut count)
+        };
+
+        let mut deleted = 0;
+
+        for idx in 0..count {
+            let response = unsafe { *responses.add(idx) };
+            let error = unsafe { rdsys::rd_kafka_DeleteAcls_result_response_error(response) };
+
+            if let Some(err) = error_string(error) {
+                bail!("Could not delete the ACLs: {}", err);
+            }
+
+            let mut matching = 0;
+            unsafe {
+                rdsys::rd_kafka_DeleteAcls_result_response_matching_acls(response, &mut matching)
+            };
+
+            deleted += matching;
+        }
+
+        println!("Deleted {} ACLs", deleted);
+
+        Ok(())
+    }
+
+    /// The ACLs matching the filter.
+    pub fn describe_acls(&self, filter: &AclFilter) -> anyhow::Result<Vec<AclEntry>> {
+        let binding = NativeAcl::filter(filter)?;
+
+        let event = self.admin_request(|rk, queue| unsafe {
+            rdsys::rd_kafka_DescribeAcls(rk, binding.0, ptr::null(), queue)
+        })?;
+
+        let mut count = 0;
+        let acls = unsafe {
+            let result = rdsys::rd_kafka_event_DescribeAcls_result(event.0);
+            rdsys::rd_kafka_DescribeAcls_result_acls(result, &mut count)
+        };
+
+        let mut entries = (0..count)
+            .map(|idx| unsafe { acl_entry(*acls.add(idx)) })
+            .collect::<Vec<AclEntry>>();
+
+        entries.sort_by(|a, b| {
+            (&a.resource_type, &a.resource, &a.principal, &a.operation).cmp(&(
+                &b.resource_type,
+                &b.resource,
+                &b.principal,
+                &b.operation,
+            ))
+        });
+
+        Ok(entries)
+    }
+
+    /// Send an admin request through a dedicated queue and wait for its result.
+    fn admin_request(
+        &self,
+        request: impl FnOnce(*mut rdsys::rd_kafka_t, *mut rdsys::rd_kafka_queue_t),
+    ) -> anyhow::Result<NativeEvent> {
+        let rk = self.consumer.client().native_ptr();
+        let queue = NativeQueue(unsafe { rdsys::rd_kafka_queue_new(rk) });
+
+        request(rk, queue.0);
+
+        let timeout_ms = REQUEST_TIMEOUT.as_millis() as i32;
+        let event = unsafe { rdsys::rd_kafka_queue_poll(queue.0, timeout_ms) };
+
+        if event.is_null() {
+            bail!("The request timed out");
+        }
+
+        let event = NativeEvent(event);
+
+        if unsafe { rdsys::rd_kafka_event_error(event.0) }
+            != RDKafkaRespErr::RD_KAFKA_RESP_ERR_NO_ERROR
+        {
+            let err = unsafe { cstr_to_string(rdsys::rd_kafka_event_error_string(event.0)) };
+            bail!("The request failed: {}", err);
+        }
+
+        Ok(event)
+    }
+}
+
+pub fn print_acls(acls: &[AclEntry], format: OutputFormat) -> anyhow::Result<()> {
+    match format {
+        OutputFormat::Text => {
+            let rows = acls
+                .iter()
+                .map(|acl| {
+                    [
+                        acl.resource_type.clone(),
+                        acl.resource.clone(),
+                        acl.pattern_type.clone(),
+                        acl.principal.clone(),
+                        acl.host.clone(),
+                        acl.operation.clone(),
+                        acl.permission.clone(),
+                    ]
+                })
+                .collect::<Vec<_>>();
+
+            terminal::print_table(
+                [
+                    "RESOURCE-TYPE",
+                    "RESOURCE",
+                    "PATTERN",
+                    "PRINCIPAL",
+                    "HOST",
+                    "OPERATION",
+                    "PERMISSION",
+                ],
+                &rows,
+            );
+        }
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(acls)?),
+    }
+
+    Ok(())
+}
+
+struct NativeAcl(*mut rd_kafka_AclBinding_t);
+
+impl NativeAcl {
+    /// The binding granting or denying one operation of a new ACL.
+    fn binding(acl: &NewAcl, operation: AclOperation) -> anyhow::Result<Self> {
+        let resource = CString::new(acl.resource.as_str())?;
+        let principal = CString::new(acl.principal.as_str())?;
+        let host = CString::new(acl.host.as_str())?;
+
+        Self::checked(|errstr, errstr_size| unsafe {
+            rdsys::rd_kafka_AclBinding_new(
+                acl.resource_type.native(),
+                resource.as_ptr(),
+                acl.pattern_type.native(),
+                principal.as_ptr(),
+                host.as_ptr(),
+                operation.native(),
+                acl.permission.native(),
+                errstr,
+                errstr_size,
+            )
+        })
+    }
+
+    /// The binding filter matching the ACLs of the filter.
+    fn filter(filter: &AclFilter) -> anyhow::Result<Self> {
+        let resource = filter.resource.as_deref().map(CString::new).transpose()?;
+        let principal = filter.principal.as_deref().map(CString::new).transpose()?;
+        let host = filter.host.as_deref().map(CString::new).transpose()?;
+
+        let as_ptr = |value: &Option<CString>| value.as_ref().map_or(ptr::null(), |v| v.as_ptr());
+
+        Self::checked(|errstr, errstr_size| unsafe {
+            rdsys::rd_kafka_AclBindingFilter_new(
+                filter.resource_type.native(),
+                as_ptr(&resource),
+                filter.pattern_type.native(),
+                as_ptr(&principal),
+                as_ptr(&host),
+                filter.operation.native(),
+                filter.permission.native(),
+                errstr,
+                errstr_size,
+            )
+        })
+        .context("Invalid ACL filter")
+    }
+
+    /// Wrap the binding created by `new`, failing with the error it reported
+    /// when it returned none.
+    fn checked(
+        new: impl FnOnce(*mut c_char, usize) -> *mut rd_kafka_AclBinding_t,
+    ) -> anyhow::Result<Self> {
+        let mut errstr = [0 as c_char; 512];
+        let binding = new(errstr.as_mut_ptr(), errstr.len());
+
+        if binding.is_null() {
+            let err = unsafe { cstr_to_string(errstr.as_ptr()) };
+            bail!("Invalid ACL: {}", err);
+        }
+
+        Ok(Self(binding))
+    }
+}
+
+impl Drop for NativeAcl {
+    fn drop(&mut self) {
+        unsafe { rdsys::rd_kafka_AclBinding_destroy(self.0) }
+    }
+}
+
+struct NativeQueue(*mut rdsys::rd_kafka_queue_t);
+
+impl Drop for NativeQueue {
+    fn drop(&mut self) {
+        unsafe { rdsys::rd_kafka_queue_destroy(self.0) }
+    }
+}
+
+struct NativeEvent(*mut rdsys::rd_kafka_event_t);
+
+impl Drop for NativeEvent {
+    fn drop(&mut self) {
+        unsafe { rdsys::rd_kafka_event_destroy(self.0) }
+    }
+}
+
+impl AclResourceType {
+    fn native(self) -> rdsys::rd_kafka_ResourceType_t {
+        use rdsys::rd_kafka_ResourceType_t::*;
+
+        match self {
+            AclResourceType::Any => RD_KAFKA_RESOURCE_ANY,
+            AclResourceType::Topic => RD_KAFKA_RESOURCE_TOPIC,
+            AclResourceType::Group => RD_KAFKA_RESOURCE_GROUP,
+            AclResourceType::Cluster => RD_KAFKA_RESOURCE_BROKER,
+        }
+    }
+}
+
+impl AclPatternType {
+    fn native(self) -> rdsys::rd_kafka_ResourcePatternType_t {
+        use rdsys::rd_kafka_ResourcePatternType_t::*;
+
+        match self {
+            AclPatternType::Any => RD_KAFKA_RESOURCE_PATTERN_ANY,
+            AclPatternType::Match => RD_KAFKA_RESOURCE_PATTERN_MATCH,
+            AclPatternType::Literal => RD_KAFKA_RESOURCE_PATTERN_LITERAL,
+            AclPatternType::Prefixed => RD_KAFKA_RESOURCE_PATTERN_PREFIXED,
+        }
+    }
+}
+
+impl AclOperation {
+    fn native(self) -> rdsys::rd_kafka_AclOperation_t {
+        use rdsys::rd_kafka_AclOperation_t::*;
+
+        match self {
+            AclOperation::Any => RD_KAFKA_ACL_OPERATION_ANY,
+            AclOperation::All => RD_KAFKA_ACL_OPERATION_ALL,
+            AclOperation::Read => RD_KAFKA_ACL_OPERATION_READ,
+            AclOperation::Write => RD_KAFKA_ACL_OPERATION_WRITE,
+            AclOperation::Create => RD_KAFKA_ACL_OPERATION_CREATE,
+            AclOperation::Delete => RD_KAFKA_ACL_OPERATION_DELETE,
+            AclOperation::Alter => RD_KAFKA_ACL_OPERATION_ALTER,
+            AclOperation::Describe => RD_KAFKA_ACL_OPERATION_DESCRIBE,
+            AclOperation::ClusterAction => RD_KAFKA_ACL_OPERATION_CLUSTER_ACTION,
+            AclOperation::DescribeConfigs => RD_KAFKA_ACL_OPERATION_DESCRIBE_CONFIGS,
+            AclOperation::AlterConfigs => RD_KAFKA_ACL_OPERATION_ALTER_CONFIGS,
+            AclOperation::IdempotentWrite => RD_KAFKA_ACL_OPERATION_IDEMPOTENT_WRITE,
+        }
+    }
+}
+
+impl AclPermission {
+    fn native(self) -> rdsys::rd_kafka_AclPermissionType_t {
+        use rdsys::rd_kafka_AclPermissionType_t::*;
+
+        match self {
+            AclPermission::Any => RD_KAFKA_ACL_PERMISSION_TYPE_ANY,
+            AclPermission::Allow => RD_KAFKA_ACL_PERMISSION_TYPE_ALLOW,
+            AclPermission::Deny => RD_KAFKA_ACL_PERMISSION_TYPE_DENY,
+        }
+    }
+}
+
+/// Read a binding returned by the broker.
+///
+/// # Safety
+///
+/// The binding must be valid, it is owned by the event it was read from.
+unsafe fn acl_entry(acl: *const rd_kafka_AclBinding_t) -> AclEntry {
+    AclEntry {
+        resource_type: cstr_to_string(rdsys::rd_kafka_ResourceType_name(
+            rdsys::rd_kafka_AclBinding_restype(acl),
+        )),
+        resource: cstr_to_string(rdsys::rd_kafka_AclBinding_name(acl)),
+        pattern_type: cstr_to_string(rdsys::rd_kafka_ResourcePatternType_name(
+            rdsys::rd_kafka_AclBinding_resource_pattern_type(acl),
+        )),
+        principal: cstr_to_string(rdsys::rd_kafka_AclBinding_principal(acl)),
+        host: cstr_to_string(rdsys::rd_kafka_AclBinding_host(acl)),
+        operation: cstr_to_string(rdsys::rd_kafka_AclOperation_name(
+            rdsys::rd_kafka_AclBinding_operation(acl),
+        )),
+        permission: cstr_to_string(rdsys::rd_kafka_AclPermissionType_name(
+            rdsys::rd_kafka_AclBinding_permission_type(acl),
+        )),
+    }
+}
+
+/// The message of an error returned by librdkafka, if any.
+fn error_string(error: *const rdsys::rd_kafka_error_t) -> Option<String> {
+    if error.is_null() {
+        return None;
+    }
+
+    Some(unsafe { cstr_to_string(rdsys::rd_kafka_error_string(error)) })
+}
+
+/// # Safety
+///
+/// The pointer must be null or point to a nul terminated string.
+unsafe fn cstr_to_string(value: *const c_char) -> String {
+    if value.is_null() {
+        return String::new();
+    }
+
+    CStr::from_ptr(value).to_string_lossy().into_owned()
+}
@@ -1,20 +1,32 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::time::Duration;
 
-use anyhow::Context;
+use anyhow::{bail, Context};
+use futures::stream::FuturesUnordered;
 use futures::StreamExt;
+use rdkafka::admin::AdminClient;
+use rdkafka::client::DefaultClientContext;
 use rdkafka::consumer::{Consumer, StreamConsumer};
-use rdkafka::message::Headers;
+use rdkafka::error::KafkaError;
+use rdkafka::message::{BorrowedMessage, Header, Headers, OwnedHeaders};
 use rdkafka::producer::{FutureProducer, FutureRecord};
+use rdkafka::types::RDKafkaErrorCode;
 use rdkafka::util::Timeout;
-use rdkafka::Message;
-use serde::{Deserialize, Serialize};
+use rdkafka::{ClientConfig, Message, Offset, TopicPartitionList};
+use serde::de::{MapAccess, Visitor};
+use serde::{Deserialize, Deserializer, Serialize};
 use serde_json::Value as JsonValue;
+use tokio::io::{AsyncBufRead, AsyncBufReadExt};
 
-use crate::{cli::CodecKind, config::KToolsContext, schema_registry::SchemaRegistryClient};
+use crate::cli::{CodecKind, OutputFormat, RawFormat};
+use crate::config::KToolsContext;
+use crate::error::{ExitCode, ExitError};
+use crate::schema_registry::{ProtoSelector, SchemaRegistryClient};
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct KafkaMessage {
-    pub key: Option<String>,
+    pub key: Option<JsonValue>,
     pub value: Option<JsonValue>,
     pub partition: i32,
     pub offset: i64,
@@ -22,10 +34,267 @@ pub struct KafkaMessage {
     pub headers: HashMap<String, String>,
 }
 
+/// A message to be produced. With `--envelope`, payload files are shaped like
+/// the [`KafkaMessage`] printed by the consumer to set every field at once.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ProduceRecord {
+    pub key: Option<JsonValue>,
+    pub value: Option<JsonValue>,
+    pub partition: Option<i32>,
+    /// Ignored, accepted so consumed messages can be produced back as they are
+    #[allow(dead_code)]
+    pub offset: Option<i64>,
+    pub timestamp: Option<i64>,
+    /// Header names may be repeated
+    #[serde(default, deserialize_with = "deserialize_headers")]
+    pub headers: Vec<(String, String)>,
+}
+
+impl ProduceRecord {
+    /// Build a record holding only the given value.
+    pub fn from_value(value: JsonValue) -> Self {
+        Self {
+            value: Some(value),
+            ..Default::default()
+        }
+    }
+
+    /// Parse a record from JSON text, either as a bare value or, when
+    /// `envelope` is set, as a [`KafkaMessage`] shaped object.
+    ///
+    /// A missing or `null` value in an envelope produces a tombstone.
+    pub fn from_json(text: &str, envelope: bool) -> serde_json::Result<Self> {
+        if envelope {
+            // Parsed from the text directly so repeated header names are kept
+            serde_json::from_str(text)
+        } else {
+            serde_json::from_str(text).map(Self::from_value)
+        }
+    }
+}
+
+/// Read headers from a JSON object, keeping every entry of a repeated name.
+fn deserialize_headers<'de, D>(deserializer: D) -> Result<Vec<(String, String)>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    struct HeadersVisitor;
+
+    impl<'de> Visitor<'de> for HeadersVisitor {
+        type Value = Vec<(String, String)>;
+
+        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+            formatter.write_str("an object of string headers")
+        }
+
+        fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+        where
+            A: MapAccess<'de>,
+        {
+            let mut headers = Vec::new();
+
+            while let Some(entry) = map.next_entry()? {
+                headers.push(entry);
+            }
+
+            Ok(headers)
+        }
+    }
+
+    deserializer.deserialize_map(HeadersVisitor)
+}
+
+/// Options controlling how records are encoded and reported by [`KafkaClient::produce`].
+#[derive(Default)]
+pub struct ProduceOptions {
+    pub encoding: CodecKind,
+    pub key_encoding: CodecKind,
+    pub proto: ProtoSelector,
+    pub format: OutputFormat,
+    /// Whether payloads are [`KafkaMessage`] shaped objects rather than bare values
+    pub envelope: bool,
+    /// Key given on the command line, replacing the key of every record
+    pub key: Option<JsonValue>,
+    /// Headers given on the command line, added to the headers of every record
+    pub headers: Vec<(String, String)>,
+    /// Partition given on the command line, replacing the partition of every record
+    pub partition: Option<i32>,
+    /// Timestamp given on the command line, replacing the timestamp of every record
+    pub timestamp: Option<i64>,
+}
+
+impl ProduceOptions {
+    fn apply_overrides(&self, record: &mut ProduceRecord) {
+        if let Some(key) = &self.key {
+            record.key = Some(key.clone());
+        }
+
+        record.headers.extend(self.headers.iter().cloned());
+        record.partition = self.partition.or(record.partition);
+        record.timestamp = self.timestamp.or(record.timestamp);
+    }
+}
+
+/// A record already encoded and ready to be sent.
+pub(super) struct EncodedRecord {
+    pub(super) key: Option<Vec<u8>>,
+    /// Missing for tombstones
+    pub(super) value: Option<Vec<u8>>,
+    pub(super) headers: Vec<(String, Option<Vec<u8>>)>,
+    pub(super) partition: Option<i32>,
+    pub(super) timestamp: Option<i64>,
+}
+
+/// Where a message was written to.
+#[derive(Debug, Serialize)]
+pub struct DeliveryReport {
+    /// The line of the JSON Lines input the message was read from
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub line: Option<usize>,
+    pub topic: String,
+    pub partition: i32,
+    pub offset: i64,
+}
+
+impl DeliveryReport {
+    fn print(&self, format: OutputFormat) -> anyhow::Result<()> {
+        match format {
+            OutputFormat::Text => {
+                if let Some(line) = self.line {
+                    print!("Line {}: ", line);
+                }
+
+                println!(
+                    "Delivered to {} partition {} at offset {}",
+                    self.topic, self.partition, self.offset
+                );
+            }
+            OutputFormat::Json => println!("{}", serde_json::to_string(self)?),
+        }
+
+        Ok(())
+    }
+}
+
+/// Outcome of a batch produce.
+#[derive(Debug, Default)]
+pub struct ProduceSummary {
+    pub delivered: usize,
+    pub failed: usize,
+    /// The exit code of the first failure
+    pub failure: Option<ExitCode>,
+}
+
+impl ProduceSummary {
+    /// Count the outcome of a message, printing its delivery report when a
+    /// format is given.
+    pub(super) fn record(
+        &mut self,
+        result: anyhow::Result<DeliveryReport>,
+        format: Option<OutputFormat>,
+    ) -> anyhow::Result<()> {
+        match result {
+            Ok(report) => {
+                self.delivered += 1;
+
+                if let Some(format) = format {
+                    report.print(format)?;
+                }
+            }
+            Err(err) => {
+                eprintln!("{:#}", err);
+                self.failed += 1;
+
+                let code = err
+                    .chain()
+                    .find_map(|cause| cause.downcast_ref::<ExitError>())
+                    .map_or(ExitCode::DeliveryFailed, |err| err.code);
+                self.failure.get_or_insert(code);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Where the consumer starts reading each assigned partition from.
+#[derive(Debug, Clone, Copy)]
+pub enum StartPosition {
+    Beginning,
+    End,
+    /// An absolute offset, or relative to the end of the partition when negative
+    Offset(i64),
+    /// The first offset whose timestamp (in milliseconds) is at or after the given one
+    Timestamp(i64),
+}
+
+/// Options controlling how messages are read and rendered by [`KafkaClient::consume`].
+pub struct ConsumeOptions {
+    pub decoding: CodecKind,
+    pub raw_format: RawFormat,
+    pub key_decoding: CodecKind,
+    /// Join this consumer group instead of peeking with manually assigned partitions
+    pub group: Option<String>,
+    /// Commit the consumed offsets to the consumer group
+    pub commit: bool,
+    /// Position to start reading the assigned partitions from, the end of the
+    /// partitions when not given
+    pub start: Option<StartPosition>,
+    /// Partitions to assign, all of the topic partitions when empty
+    pub partitions: Vec<i32>,
+    /// Stop after printing this many messages
+    pub max_messages: Option<usize>,
+    /// Stop once every partition reaches the high watermark seen at start
+    pub until_end: bool,
+    /// Stop each partition after the message at this offset, or at the end it
+    /// has at start when it doesn't reach the offset
+    pub until_offset: Option<i64>,
+    /// Stop each partition at the first message after this timestamp (in milliseconds)
+    pub until_timestamp: Option<i64>,
+}
+
+impl ConsumeOptions {
+    fn is_bounded_by_partition(&self) -> bool {
+        self.until_end || self.until_offset.is_some() || self.until_timestamp.is_some()
+    }
+}
+
+/// Keeps track of the partitions that already reached the stop conditions of
+/// a bounded consume.
+struct ConsumeBounds {
+    partitions: Vec<i32>,
+    end_offsets: HashMap<i32, i64>,
+    finished: HashSet<i32>,
+    printed: usize,
+}
+
+impl ConsumeBounds {
+    fn is_done(&self, options: &ConsumeOptions) -> bool {
+        let all_finished = options.is_bounded_by_partition()
+            && self.partitions.iter().all(|p| self.finished.contains(p));
+
+        let max_reached = options
+            .max_messages
+            .is_some_and(|max_messages| self.printed >= max_messages);
+
+        all_finished || max_reached
+    }
+}
+
+/// Timeout for metadata and offset lookups.
+pub(super) const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How long a bounded consume waits for messages before checking whether the
+/// partitions already reached their end.
+const IDLE_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
 pub struct KafkaClient {
+    pub(super) config: ClientConfig,
     producer: FutureProducer,
-    consumer: StreamConsumer,
-    schema_registry: SchemaRegistryClient,
+    delivery_timeout: Timeout,
+    pub(super) consumer: StreamConsumer,
+    pub(super) schema_registry: SchemaRegistryClient,
 }
 
 impl KafkaClient {
@@ -35,12 +304,9 @@ impl KafkaClient {
             .as_ref()
             .context("No kafka configuration found")?;
 
-        let group_id = format!("ktools-{}", user);
-
         let mut rdkafka_config = rdkafka::ClientConfig::new();
         rdkafka_config.set("bootstrap.servers", &ktools_kafka_config.bootstrap_server);
         rdkafka_config.set("client.id", "ktools-cli");
-        rdkafka_config.set("group.id", group_id);
 
         if let Some(props) = &ktools_kafka_config.properties {
             for (key, value) in props {
@@ -50,77 +316,759 @@ impl KafkaClient {
 
         let schema_registry = SchemaRegistryClient::configure(context)?;
 
+        // The default consumer only peeks at topics: its group is unique to this
+        // process and it never commits, so no real consumer group is affected
+        let group_id = format!("ktools-{}-{}", user, std::process::id());
+
+        let consumer = rdkafka_config
+            .clone()
+            .set("group.id", group_id)
+            .set("enable.auto.commit", "false")
+            .create()?;
+
         Ok(Self {
             producer: rdkafka_config.create()?,
-            consumer: rdkafka_config.create()?,
+            delivery_timeout: Timeout::Never,
+            consumer,
+            config: rdkafka_config,
             schema_registry,
         })
     }
 
+    /// Create an admin client sharing the configuration of the other clients.
+    pub(super) fn admin(&self) -> anyhow::Result<AdminClient<DefaultClientContext>> {
+        Ok(self.config.create()?)
+    }
+
+    /// Recreate the producer so messages not delivered within `timeout` fail
+    /// instead of being retried indefinitely.
+    pub fn set_delivery_timeout(&mut self, timeout: Duration) -> anyhow::Result<()> {
+        self.producer = self
+            .config
+            .clone()
+            .set("message.timeout.ms", timeout.as_millis().to_string())
+            .create()?;
+        self.delivery_timeout = Timeout::After(timeout);
+
+        Ok(())
+    }
+
     pub async fn produce(
         self,
-        codec: CodecKind,
         topic: &str,
-        key: Option<String>,
-        payload: Vec<u8>,
+        mut record: ProduceRecord,
+        options: &ProduceOptions,
     ) -> anyhow::Result<()> {
-        let key = key.as_ref().map(|k| k.as_bytes()).unwrap_or_default();
-        let value = self.schema_registry.encode(codec, topic, &payload).await?;
+        options.apply_overrides(&mut record);
+
+        let record = self
+            .encode_record(topic, record, options)
+            .await
+            .map_err(|err| {
+                ExitError::new(
+                    ExitCode::InvalidMessage,
+                    format!("Could not encode the message: {:#}", err),
+                )
+            })?;
 
-        let record = FutureRecord::to(topic).key(key).payload(&value);
+        let (partition, offset) = self
+            .send(topic, &record)
+            .await
+            .map_err(|err| delivery_error(&err))?;
 
-        if let Err((e, _)) = self.producer.send(record, Timeout::Never).await {
-            eprintln!("Failed to produce message: {}", e);
+        DeliveryReport {
+            line: None,
+            topic: topic.to_string(),
+            partition,
+            offset,
         }
+        .print(options.format)
+    }
 
-        Ok(())
+    /// Produce every line of a JSON Lines input, each line being either a bare
+    /// value or a [`KafkaMessage`] shaped object.
+    ///
+    /// At most `max_in_flight` messages are being encoded or waiting for their
+    /// delivery at any given time.
+    pub async fn produce_batch<R>(
+        self,
+        topic: &str,
+        input: R,
+        options: &ProduceOptions,
+        max_in_flight: usize,
+    ) -> anyhow::Result<ProduceSummary>
+    where
+        R: AsyncBufRead + Unpin,
+    {
+        let mut lines = input.lines();
+        let mut line_number = 0;
+        let mut in_flight = FuturesUnordered::new();
+        let mut summary = ProduceSummary::default();
+
+        let mut reading = true;
+
+        // Deliveries keep progressing while waiting for the next line, which
+        // matters when lines trickle in from stdin
+        loop {
+            tokio::select! {
+                Some(result) = in_flight.next(), if !in_flight.is_empty() => {
+                    summary.record(result, Some(options.format))?;
+                }
+                line = lines.next_line(), if reading && in_flight.len() < max_in_flight.max(1) => {
+                    let Some(line) = line? else {
+                        reading = false;
+                        continue;
+                    };
+
+                    line_number += 1;
+
+                    if !line.trim().is_empty() {
+                        in_flight.push(self.produce_line(topic, line_number, line, options));
+                    }
+                }
+                else => break,
+            }
+        }
+
+        Ok(summary)
     }
 
-    pub async fn consume(self, topic: &str, decoding: CodecKind) -> anyhow::Result<()> {
-        let consumer = self.consumer;
-        consumer.subscribe(&[topic]).unwrap();
+    async fn produce_line(
+        &self,
+        topic: &str,
+        line_number: usize,
+        line: String,
+        options: &ProduceOptions,
+    ) -> anyhow::Result<DeliveryReport> {
+        let invalid = |message: &str| {
+            ExitError::new(
+                ExitCode::InvalidMessage,
+                format!("Line {}: {}", line_number, message),
+            )
+        };
 
-        let mut message_stream = consumer.stream();
+        let mut record = ProduceRecord::from_json(&line, options.envelope)
+            .with_context(|| invalid("invalid JSON message"))?;
+        options.apply_overrides(&mut record);
 
-        while let Some(Ok(message)) = message_stream.next().await {
-            let payload = message.payload();
-            let key = message
-                .key()
-                .map(|key| String::from_utf8_lossy(key).to_string());
+        let record = self
+            .encode_record(topic, record, options)
+            .await
+            .with_context(|| invalid("could not encode the message"))?;
 
-            let headers = if let Some(headers) = message.headers() {
-                let mut h = HashMap::new();
+        let (partition, offset) = self.send(topic, &record).await.map_err(|err| {
+            let err = delivery_error(&err);
+            ExitError::new(err.code, format!("Line {}: {}", line_number, err.message))
+        })?;
 
-                for header in headers.iter() {
-                    let key = String::from_utf8_lossy(header.key.as_bytes()).to_string();
-                    let value = header
-                        .value
-                        .as_ref()
-                        .map(|v| String::from_utf8_lossy(v).to_string())
-                        .unwrap_or_default();
+        Ok(DeliveryReport {
+            line: Some(line_number),
+            topic: topic.to_string(),
+            partition,
+            offset,
+        })
+    }
 
-                    h.insert(key, value);
-                }
+    async fn encode_record(
+        &self,
+        topic: &str,
+        record: ProduceRecord,
+        options: &ProduceOptions,
+    ) -> anyhow::Result<EncodedRecord> {
+        let key = match record.key {
+            // A missing or null key sends the message without a key
+            None | Some(JsonValue::Null) => None,
+            Some(key) => {
+                let key = match (options.key_encoding, key) {
+                    // Raw string keys are sent as they are, without JSON quotes
+                    (CodecKind::Raw, JsonValue::String(key)) => key.into_bytes(),
+                    (_, key) => serde_json::to_vec(&key)?,
+                };
 
-                h
-            } else {
-                HashMap::new()
-            };
+                let key = self
+                    .schema_registry
+                    .encode(
+                        options.key_encoding,
+                        topic,
+                        true,
+                        &key,
+                        &ProtoSelector::default(),
+                    )
+                    .await?;
+
+                Some(key)
+            }
+        };
 
-            let payload = self.schema_registry.decode(decoding, payload).await?;
+        let value = match record.value {
+            // Tombstones are sent without a payload
+            None | Some(JsonValue::Null) => None,
+            Some(value) => {
+                let payload = serde_json::to_vec(&value)?;
+                let value = self
+                    .schema_registry
+                    .encode(options.encoding, topic, false, &payload, &options.proto)
+                    .await?;
 
-            let message = KafkaMessage {
+                Some(value)
+            }
+        };
+
+        let headers = record
+            .headers
+            .into_iter()
+            .map(|(key, value)| (key, Some(value.into_bytes())))
+            .collect();
+
+        Ok(EncodedRecord {
+            key,
+            value,
+            headers,
+            partition: record.partition,
+            timestamp: record.timestamp,
+        })
+    }
+
+    /// Send an encoded record, returning the partition and offset it was written to.
+    pub(super) async fn send(
+        &self,
+        topic: &str,
+        record: &EncodedRecord,
+    ) -> Result<(i32, i64), KafkaError> {
+        let mut headers = OwnedHeaders::new_with_capacity(record.headers.len());
+
+        for (key, value) in &record.headers {
+            headers = headers.insert(Header {
                 key,
-                value: payload,
-                partition: message.partition(),
-                offset: message.offset(),
-                timestamp: message.timestamp().to_millis().unwrap_or_default(),
-                headers,
+                value: value.as_deref(),
+            });
+        }
+
+        let mut future_record = FutureRecord::<[u8], [u8]>::to(topic).headers(headers);
+
+        if let Some(key) = &record.key {
+            future_record = future_record.key(key);
+        }
+
+        if let Some(value) = &record.value {
+            future_record = future_record.payload(value);
+        }
+
+        if let Some(partition) = record.partition {
+            future_record = future_record.partition(partition);
+        }
+
+        if let Some(timestamp) = record.timestamp {
+            future_record = future_record.timestamp(timestamp);
+        }
+
+        self.producer
+            .send(future_record, self.delivery_timeout)
+            .await
+            .map_err(|(err, _)| err)
+    }
+
+    pub async fn consume(self, topic: &str, options: ConsumeOptions) -> anyhow::Result<()> {
+        let consumer: StreamConsumer = match &options.group {
+            Some(group) => self
+                .config
+                .clone()
+                .set("group.id", group)
+                .set("enable.auto.commit", options.commit.to_string())
+                .create()?,
+            None => self.consumer,
+        };
+
+        // Without a group the partitions are always assigned manually
+        let subscribe =
+            options.group.is_some() && options.start.is_none() && options.partitions.is_empty();
+
+        // The partitions of a subscription are only known once the group assigns
+        // them, so they can't be bounded
+        if subscribe && options.is_bounded_by_partition() {
+            bail!(
+                "Stopping at the end, an offset or a timestamp with a consumer group \
+                 requires a start position or explicit partitions"
+            );
+        }
+
+        if subscribe {
+            consumer.subscribe(&[topic])?;
+        } else {
+            let assignment = Self::start_assignment(
+                &consumer,
+                topic,
+                &options.partitions,
+                options.start.unwrap_or(StartPosition::End),
+            )?;
+            consumer.assign(&assignment)?;
+        }
+
+        let mut bounds = ConsumeBounds {
+            partitions: Vec::new(),
+            end_offsets: HashMap::new(),
+            finished: HashSet::new(),
+            printed: 0,
+        };
+
+        if options.is_bounded_by_partition() {
+            bounds.partitions = match options.partitions.as_slice() {
+                [] => Self::topic_partitions(&consumer, topic)?,
+                partitions => partitions.to_vec(),
             };
+        }
+
+        if options.until_end || options.until_offset.is_some() {
+            for &partition in &bounds.partitions {
+                let (low, high) = consumer.fetch_watermarks(topic, partition, REQUEST_TIMEOUT)?;
+
+                // Partitions that don't reach `until_offset` yet are read up to their end
+                let end = options
+                    .until_offset
+                    .map_or(high, |until| high.min(until + 1));
+
+                if low >= end {
+                    bounds.finished.insert(partition);
+                }
+
+                bounds.end_offsets.insert(partition, end);
+            }
+        }
+
+        let mut message_stream = consumer.stream();
+
+        while !bounds.is_done(&options) {
+            let message =
+                match tokio::time::timeout(IDLE_CHECK_INTERVAL, message_stream.next()).await {
+                    Ok(Some(Ok(message))) => message,
+                    Ok(Some(Err(err))) => {
+                        return Err(err).with_context(|| format!("Failed to consume {}", topic))
+                    }
+                    Ok(None) => break,
+                    Err(_) => {
+                        if !bounds.end_offsets.is_empty() {
+                            Self::finish_partitions_at_end(&consumer, topic, &mut bounds)?;
+                        }
+
+                        continue;
+                    }
+                };
+
+            let partition = message.partition();
+            let offset = message.offset();
+            let timestamp = message.timestamp().to_millis().unwrap_or_default();
+
+            // Messages of finished partitions may still be buffered
+            if bounds.finished.contains(&partition) {
+                continue;
+            }
+
+            let past_offset = options.until_offset.is_some_and(|until| offset > until);
+            let past_timestamp = options
+                .until_timestamp
+                .is_some_and(|until| timestamp > until);
+
+            if past_offset || past_timestamp {
+                Self::finish_partition(&consumer, topic, partition, &mut bounds);
+                continue;
+            }
+
+            let message = decode_message(
+                &self.schema_registry,
+                &message,
+                options.decoding,
+                options.raw_format,
+                options.key_decoding,
+            )
+            .await?;
 
             println!("{}", serde_json::to_string_pretty(&message)?);
+            bounds.printed += 1;
+
+            let reached_offset = options.until_offset.is_some_and(|until| offset >= until);
+            let reached_end = bounds
+                .end_offsets
+                .get(&partition)
+                .is_some_and(|&end| offset + 1 >= end);
+
+            if reached_offset || reached_end {
+                Self::finish_partition(&consumer, topic, partition, &mut bounds);
+            }
         }
 
         Ok(())
     }
+
+    /// Fetch the message at the offset of a partition, failing when there is none.
+    pub async fn get(
+        self,
+        topic: &str,
+        partition: i32,
+        offset: i64,
+        decoding: CodecKind,
+        raw_format: RawFormat,
+        key_decoding: CodecKind,
+    ) -> anyhow::Result<()> {
+        let (low, high) = self
+            .consumer
+            .fetch_watermarks(topic, partition, REQUEST_TIMEOUT)?;
+
+        if offset < low || offset >= high {
+            bail!(
+                "Offset {} is out of range, partition {} holds offsets {} to {}",
+                offset,
+                partition,
+                low,
+                high - 1
+            );
+        }
+
+        let mut assignment = TopicPartitionList::new();
+        assignment.add_partition_offset(topic, partition, Offset::Offset(offset))?;
+        self.consumer.assign(&assignment)?;
+
+        let message = tokio::time::timeout(REQUEST_TIMEOUT, self.consumer.recv())
+            .await
+            .with_context(|| format!("Timed out fetching offset {}", offset))??;
+
+        // Offsets of compacted messages and transaction markers are skipped
+        if message.offset() != offset {
+            bail!(
+                "No message at offset {}, the next one is at offset {}",
+                offset,
+                message.offset()
+            );
+        }
+
+        let message = decode_message(
+            &self.schema_registry,
+            &message,
+            decoding,
+            raw_format,
+            key_decoding,
+        )
+        .await?;
+
+        println!("{}", serde_json::to_string_pretty(&message)?);
+
+        Ok(())
+    }
+
+    /// Assign the partitions positioned at `start`, returning the offset each one
+    /// is read up to: the end it has now, or right after `until_offset`.
+    ///
+    /// Partitions that have nothing to be read are left out.
+    pub(super) fn assign_range(
+        &self,
+        topic: &str,
+        partitions: &[i32],
+        start: StartPosition,
+        until_offset: Option<i64>,
+    ) -> anyhow::Result<HashMap<i32, i64>> {
+        let assignment = Self::start_assignment(&self.consumer, topic, partitions, start)?;
+        self.consumer.assign(&assignment)?;
+
+        let mut end_offsets = HashMap::new();
+
+        for element in assignment.elements() {
+            let partition = element.partition();
+            let (_, high) = self
+                .consumer
+                .fetch_watermarks(topic, partition, REQUEST_TIMEOUT)?;
+
+            let end = until_offset.map_or(high, |until| high.min(until + 1));
+
+            let finished = match element.offset() {
+                Offset::End => true,
+                Offset::Offset(start) => start >= end,
+                _ => false,
+            };
+
+            if !finished {
+                end_offsets.insert(partition, end);
+            }
+        }
+
+        Ok(end_offsets)
+    }
+
+    /// Receive the next message of a range assigned by [`Self::assign_range`],
+    /// removing partitions from `end_offsets` as they finish. Returns `None` once
+    /// every partition is finished.
+    pub(super) async fn next_in_range(
+        &self,
+        topic: &str,
+        end_offsets: &mut HashMap<i32, i64>,
+        until_timestamp: Option<i64>,
+    ) -> anyhow::Result<Option<BorrowedMessage<'_>>> {
+        while !end_offsets.is_empty() {
+            let message =
+                match tokio::time::timeout(IDLE_CHECK_INTERVAL, self.consumer.recv()).await {
+                    Ok(message) => message?,
+                    Err(_) => {
+                        self.finish_partitions_in_range(topic, end_offsets)?;
+                        continue;
+                    }
+                };
+
+            let partition = message.partition();
+            let offset = message.offset();
+
+            let Some(&end) = end_offsets.get(&partition) else {
+                continue;
+            };
+
+            let past_timestamp = until_timestamp.is_some_and(|until| {
+                message
+                    .timestamp()
+                    .to_millis()
+                    .is_some_and(|timestamp| timestamp > until)
+            });
+
+            if offset >= end || past_timestamp {
+                end_offsets.remove(&partition);
+                continue;
+            }
+
+            if offset + 1 >= end {
+                end_offsets.remove(&partition);
+            }
+
+            return Ok(Some(message));
+        }
+
+        Ok(None)
+    }
+
+    /// Remove the partitions whose position already reached their end offset,
+    /// which happens when no message is left to be read from them.
+    fn finish_partitions_in_range(
+        &self,
+        topic: &str,
+        end_offsets: &mut HashMap<i32, i64>,
+    ) -> anyhow::Result<()> {
+        for element in self.consumer.position()?.elements_for_topic(topic) {
+            if let (Offset::Offset(position), Some(&end)) =
+                (element.offset(), end_offsets.get(&element.partition()))
+            {
+                if position >= end {
+                    end_offsets.remove(&element.partition());
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Mark the partition as finished and stop fetching from it.
+    fn finish_partition(
+        consumer: &StreamConsumer,
+        topic: &str,
+        partition: i32,
+        bounds: &mut ConsumeBounds,
+    ) {
+        bounds.finished.insert(partition);
+
+        let mut paused = TopicPartitionList::new();
+        paused.add_partition(topic, partition);
+
+        // Pausing only avoids fetching messages that would be skipped anyway
+        let _ = consumer.pause(&paused);
+    }
+
+    /// Finish the partitions whose position already reached the end offset,
+    /// which happens when no message is left to be read from them.
+    fn finish_partitions_at_end(
+        consumer: &StreamConsumer,
+        topic: &str,
+        bounds: &mut ConsumeBounds,
+    ) -> anyhow::Result<()> {
+        for element in consumer.position()?.elements_for_topic(topic) {
+            let partition = element.partition();
+
+            let at_end = match (element.offset(), bounds.end_offsets.get(&partition)) {
+                (Offset::Offset(position), Some(&end)) => position >= end,
+                _ => false,
+            };
+
+            if at_end && !bounds.finished.contains(&partition) {
+                Self::finish_partition(consumer, topic, partition, bounds);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Build the assignment for the given partitions (or every partition of the
+    /// topic) positioned at `start`.
+    fn start_assignment(
+        consumer: &StreamConsumer,
+        topic: &str,
+        partitions: &[i32],
+        start: StartPosition,
+    ) -> anyhow::Result<TopicPartitionList> {
+        let partitions = match partitions {
+            [] => Self::topic_partitions(consumer, topic)?,
+            partitions => partitions.to_vec(),
+        };
+
+        let offset = match start {
+            StartPosition::Beginning => Offset::Beginning,
+            StartPosition::End => Offset::End,
+            StartPosition::Offset(offset) if offset < 0 => Offset::OffsetTail(-offset),
+            StartPosition::Offset(offset) => Offset::Offset(offset),
+            StartPosition::Timestamp(timestamp) => Offset::Offset(timestamp),
+        };
+
+        let mut assignment = TopicPartitionList::new();
+
+        for partition in partitions {
+            assignment.add_partition_offset(topic, partition, offset)?;
+        }
+
+        // Offsets hold the timestamps to look up, partitions without messages
+        // after the timestamp are positioned at the end
+        if let StartPosition::Timestamp(_) = start {
+            assignment = consumer.offsets_for_times(assignment, REQUEST_TIMEOUT)?;
+        }
+
+        Ok(assignment)
+    }
+
+    pub(super) fn topic_partitions(
+        consumer: &StreamConsumer,
+        topic: &str,
+    ) -> anyhow::Result<Vec<i32>> {
+        let metadata = consumer.fetch_metadata(Some(topic), REQUEST_TIMEOUT)?;
+
+        let topic_metadata = metadata
+            .topics()
+            .iter()
+            .find(|t| t.name() == topic)
+            .with_context(|| format!("Topic {} not found", topic))?;
+
+        if let Some(err) = topic_metadata.error() {
+            bail!("Could not fetch the metadata of topic {}: {:?}", topic, err);
+        }
+
+        Ok(topic_metadata.partitions().iter().map(|p| p.id()).collect())
+    }
+}
+
+/// Decode the key and value of a consumed message.
+pub(super) async fn decode_message<M: Message>(
+    schema_registry: &SchemaRegistryClient,
+    message: &M,
+    decoding: CodecKind,
+    raw_format: RawFormat,
+    key_decoding: CodecKind,
+) -> anyhow::Result<KafkaMessage> {
+    let key = schema_registry
+        .decode(key_decoding, RawFormat::Text, message.key())
+        .await?;
+
+    let value = schema_registry
+        .decode(decoding, raw_format, message.payload())
+        .await?;
+
+    Ok(KafkaMessage {
+        key,
+        value,
+        partition: message.partition(),
+        offset: message.offset(),
+        timestamp: message.timestamp().to_millis().unwrap_or_default(),
+        headers: message_headers(message),
+    })
+}
+
+/// The headers of a message, with values read as text.
+pub(super) fn message_headers<M: Message>(message: &M) -> HashMap<String, String> {
+    let mut headers = HashMap::new();
+
+    if let Some(message_headers) = message.headers() {
+        for header in message_headers.iter() {
+            let value = header
+                .value
+                .map(|v| String::from_utf8_lossy(v).to_string())
+                .unwrap_or_default();
+
+            headers.insert(header.key.to_string(), value);
+        }
+    }
+
+    headers
+}
+
+/// Map a failed delivery to the matching exit code.
+fn delivery_error(err: &KafkaError) -> ExitError {
+    match err.rdkafka_error_code() {
+        Some(RDKafkaErrorCode::MessageTimedOut) => ExitError::new(
+            ExitCode::DeliveryTimedOut,
+            format!("Message not delivered within the delivery timeout: {}", err),
+        ),
+        _ => ExitError::new(
+            ExitCode::DeliveryFailed,
+            format!("Failed to produce message: {}", err),
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bare_values_are_not_unwrapped() {
+        let record = ProduceRecord::from_json(r#"{"value": 3}"#, false).unwrap();
+
+        assert_eq!(record.value, Some(serde_json::json!({ "value": 3 })));
+        assert_eq!(record.key, None);
+        assert!(record.headers.is_empty());
+    }
+
+    #[test]
+    fn envelopes_set_every_field() {
+        let record = ProduceRecord::from_json(
+            r#"{
+                "key": "id-1",
+                "value": {"name": "test"},
+                "partition": 2,
+                "offset": 10,
+                "timestamp": 1700000000000,
+                "headers": {"source": "a", "trace": "b", "source": "c"}
+            }"#,
+            true,
+        )
+        .unwrap();
+
+        assert_eq!(record.key, Some(serde_json::json!("id-1")));
+        assert_eq!(record.value, Some(serde_json::json!({ "name": "test" })));
+        assert_eq!(record.partition, Some(2));
+        assert_eq!(record.timestamp, Some(1700000000000));
+        assert_eq!(
+            record.headers,
+            [
+                ("source".to_string(), "a".to_string()),
+                ("trace".to_string(), "b".to_string()),
+                ("source".to_string(), "c".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn envelopes_without_a_value_are_tombstones() {
+        for text in [r#"{"key": "id-1"}"#, r#"{"key": "id-1", "value": null}"#] {
+            let record = ProduceRecord::from_json(text, true).unwrap();
+
+            assert_eq!(record.key, Some(serde_json::json!("id-1")));
+            assert_eq!(record.value, None);
+        }
+    }
+
+    #[test]
+    fn envelopes_with_unknown_fields_are_rejected() {
+        assert!(ProduceRecord::from_json(r#"{"value": 1, "topic": "a"}"#, true).is_err());
+        assert!(ProduceRecord::from_json(r#"{"value": 1, "headers": {"a": 1}}"#, true).is_err());
+        assert!(ProduceRecord::from_json("[1, 2]", true).is_err());
+    }
 }
@@ -0,0 +1,239 @@
+use std::collections::BTreeMap;
+
+use rdkafka::bindings;
+use rdkafka::consumer::Consumer;
+use serde::Serialize;
+
+use crate::cli::OutputFormat;
+use crate::error::{ExitCode, ExitError};
+use crate::kafka::client::{KafkaClient, REQUEST_TIMEOUT};
+use crate::kafka::topic::join_ids;
+use crate::terminal;
+
+/// Share of the partitions preferring a broker that may be led by other brokers
+/// before the cluster is reported as imbalanced, as Kafka's own default.
+const LEADER_IMBALANCE_RATIO: f64 = 0.1;
+
+#[derive(Debug, Serialize)]
+pub struct ClusterReport {
+    /// Missing when no broker reported being the controller
+    pub controller: Option<i32>,
+    pub brokers: Vec<BrokerReport>,
+    pub topics: usize,
+    pub partitions: usize,
+    pub under_replicated: Vec<PartitionProblem>,
+    pub offline: Vec<PartitionProblem>,
+    /// Brokers leading too few of the partitions they are the preferred leader of
+    pub imbalanced_brokers: Vec<i32>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BrokerReport {
+    pub id: i32,
+    pub host: String,
+    pub port: i32,
+    /// Partitions currently led by the broker
+    pub leaders: usize,
+    /// Partitions whose first replica is the broker
+    pub preferred_leaders: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PartitionProblem {
+    pub topic: String,
+    pub partition: i32,
+    pub leader: i32,
+    pub replicas: Vec<i32>,
+    pub isr: Vec<i32>,
+}
+
+impl ClusterReport {
+    pub fn is_healthy(&self) -> bool {
+        self.controller.is_some()
+            && self.under_replicated.is_empty()
+            && self.offline.is_empty()
+            && self.imbalanced_brokers.is_empty()
+    }
+}
+
+impl KafkaClient {
+    /// Print an overview of the cluster, failing when it has problems.
+    pub fn cluster_health(&self, format: OutputFormat) -> anyhow::Result<()> {
+        let report = self.cluster_report()?;
+
+        match format {
+            OutputFormat::Text => {
+                match report.controller {
+                    Some(controller) => println!("Controller: {}", controller),
+                    None => println!("Controller: unknown"),
+                }
+                println!("Topics:     {}", report.topics);
+                println!("Partitions: {}", report.partitions);
+                println!();
+
+                let rows = report
+                    .brokers
+                    .iter()
+                    .map(|broker| {
+                        [
+                            broker.id.to_string(),
+                            format!("{}:{}", broker.host, broker.port),
+                            broker.leaders.to_string(),
+                            broker.preferred_leaders.to_string(),
+                        ]
+                    })
+                    .collect::<Vec<_>>();
+
+                terminal::print_table(["BROKER", "ADDRESS", "LEADERS", "PREFERRED"], &rows);
+
+                for (title, problems) in [
+                    ("Under-replicated partitions", &report.under_replicated),
+                    ("Offline partitions", &report.offline),
+                ] {
+                    if problems.is_empty() {
+                        continue;
+                    }
+
+                    println!();
+                    println!("{}:", title);
+
+                    let rows = problems
+                        .iter()
+                        .map(|problem| {
+                            [
+                                problem.topic.clone(),
+                                problem.partition.to_string(),
+                                problem.leader.to_string(),
+                                join_ids(&problem.replicas),
+                                join_ids(&problem.isr),
+                            ]
+                        })
+                        .collect::<Vec<_>>();
+
+                    terminal::print_table(
+                        ["TOPIC", "PARTITION", "LEADER", "REPLICAS", "ISR"],
+                        &rows,
+                    );
+                }
+
+                if !report.imbalanced_brokers.is_empty() {
+                    println!();
+                    println!(
+                        "Leader imbalance on brokers: {}",
+                        join_ids(&report.imbalanced_brokers)
+                    );
+                }
+            }
+            OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&report)?),
+        }
+
+        if !report.is_healthy() {
+            return Err(ExitError::new(
+                ExitCode::ClusterUnhealthy,
+                "Problems were detected in the cluster",
+            )
+            .into());
+        }
+
+        Ok(())
+    }
+
+    fn cluster_report(&self) -> anyhow::Result<ClusterReport> {
+        let metadata = self.consumer.fetch_metadata(None, REQUEST_TIMEOUT)?;
+
+        let mut brokers = metadata
+            .brokers()
+            .iter()
+            .map(|broker| {
+                let report = BrokerReport {
+                    id: broker.id(),
+                    host: broker.host().to_string(),
+                    port: broker.port(),
+                    leaders: 0,
+                    preferred_leaders: 0,
+                };
+
+                (broker.id(), report)
+            })
+            .collect::<BTreeMap<i32, BrokerReport>>();
+
+        let mut report = ClusterReport {
+            controller: self.controller_id(),
+            brokers: Vec::new(),
+            topics: 0,
+            partitions: 0,
+            under_replicated: Vec::new(),
+            offline: Vec::new(),
+            imbalanced_brokers: Vec::new(),
+        };
+
+        // Partitions whose preferred leader is the broker but are led by another one
+        let mut moved_leaders = BTreeMap::<i32, usize>::new();
+
+        for topic in metadata.topics() {
+            report.topics += 1;
+
+            for partition in topic.partitions() {
+                report.partitions += 1;
+
+                let problem = || PartitionProblem {
+                    topic: topic.name().to_string(),
+                    partition: partition.id(),
+                    leader: partition.leader(),
+                    replicas: partition.replicas().to_vec(),
+                    isr: partition.isr().to_vec(),
+                };
+
+                if partition.leader() < 0 {
+                    report.offline.push(problem());
+                    continue;
+                }
+
+                if partition.isr().len() < partition.replicas().len() {
+                    report.under_replicated.push(problem());
+                }
+
+                if let Some(broker) = brokers.get_mut(&partition.leader()) {
+                    broker.leaders += 1;
+                }
+
+                if let Some(&preferred) = partition.replicas().first() {
+                    if let Some(broker) = brokers.get_mut(&preferred) {
+                        broker.preferred_leaders += 1;
+                    }
+
+                    if preferred != partition.leader() {
+                        *moved_leaders.entry(preferred).or_default() += 1;
+                    }
+                }
+            }
+        }
+
+        for broker in brokers.values() {
+            let moved = moved_leaders.get(&broker.id).copied().unwrap_or_default();
+
+            if broker.preferred_leaders > 0
+                && moved as f64 / broker.preferred_leaders as f64 > LEADER_IMBALANCE_RATIO
+            {
+                report.imbalanced_brokers.push(broker.id);
+            }
+        }
+
+        report.brokers = brokers.into_values().collect();
+
+        Ok(report)
+    }
+
+    /// The id of the controller broker, which the metadata API of the Rust client
+    /// does not expose.
+    fn controller_id(&self) -> Option<i32> {
+        let timeout_ms = REQUEST_TIMEOUT.as_millis() as i32;
+
+        // SAFETY: the pointer is owned by the consumer, which outlives the call
+        let id = unsafe {
+            bindings::rd_kafka_controllerid(self.consumer.client().native_ptr(), timeout_ms)
+        };
+
+        (id >= 0).then_some(id)
+    }
+}
@@ -0,0 +1,272 @@
+use std::collections::BTreeMap;
+
+use anyhow::{bail, Context};
+use rdkafka::admin::{AdminOptions, AlterConfig, ConfigEntry, ConfigSource, ResourceSpecifier};
+use serde::Serialize;
+
+use crate::cli::OutputFormat;
+use crate::kafka::client::{KafkaClient, REQUEST_TIMEOUT};
+use crate::terminal;
+
+/// A topic or a broker whose configuration is described or altered.
+#[derive(Debug, Clone)]
+pub enum ConfigResource {
+    Topic(String),
+    Broker(i32),
+}
+
+impl ConfigResource {
+    fn specifier(&self) -> ResourceSpecifier<'_> {
+        match self {
+            ConfigResource::Topic(topic) => ResourceSpecifier::Topic(topic),
+            ConfigResource::Broker(id) => ResourceSpecifier::Broker(*id),
+        }
+    }
+
+    /// Whether the entry was set dynamically on this very resource, which are
+    /// the entries replaced when altering its configuration.
+    fn is_own_dynamic(&self, entry: &ConfigEntry) -> bool {
+        match self {
+            ConfigResource::Topic(_) => entry.source == ConfigSource::DynamicTopic,
+            ConfigResource::Broker(_) => entry.source == ConfigSource::DynamicBroker,
+        }
+    }
+}
+
+impl std::fmt::Display for ConfigResource {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            ConfigResource::Topic(topic) => write!(f, "topic {}", topic),
+            ConfigResource::Broker(id) => write!(f, "broker {}", id),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct ConfigDescription {
+    pub name: String,
+    pub value: Option<String>,
+    pub source: &'static str,
+    pub read_only: bool,
+    pub sensitive: bool,
+}
+
+/// A change to be applied to a configuration entry, `None` meaning the entry
+/// goes back to its default.
+struct ConfigChange {
+    name: String,
+    current: Option<String>,
+    new: Option<String>,
+}
+
+impl KafkaClient {
+    pub async fn describe_configs(
+        &self,
+        resource: &ConfigResource,
+        format: OutputFormat,
+    ) -> anyhow::Result<()> {
+        let entries = self.config_entries(resource).await?;
+
+        let configs = entries
+            .iter()
+            .map(|entry| ConfigDescription {
+                name: entry.name.clone(),
+                value: entry.value.clone(),
+                source: source_name(&entry.source),
+                read_only: entry.is_read_only,
+                sensitive: entry.is_sensitive,
+            })
+            .collect::<Vec<ConfigDescription>>();
+
+        match format {
+            OutputFormat::Text => {
+                let rows = configs
+                    .iter()
+                    .map(|config| {
+                        let value = match (&config.value, config.sensitive) {
+                            (_, true) => "(sensitive)".into(),
+                            (Some(value), _) => value.clone(),
+                            (None, _) => "-".into(),
+                        };
+
+                        [
+                            config.name.clone(),
+                            value,
+                            config.source.to_string(),
+                            if config.read_only { "yes" } else { "no" }.to_string(),
+                        ]
+                    })
+                    .collect::<Vec<_>>();
+
+                terminal::print_table(["NAME", "VALUE", "SOURCE", "READ-ONLY"], &rows);
+            }
+            OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&configs)?),
+        }
+
+        Ok(())
+    }
+
+    /// Set and reset configuration entries, printing the changes before applying them.
+    ///
+    /// The brokers replace every dynamic entry of the resource on alter, so the
+    /// entries that are not being changed are sent again with their current values.
+    pub async fn alter_configs(
+        &self,
+        resource: &ConfigResource,
+        set: &[(String, String)],
+        reset: &[String],
+        dry_run: bool,
+        yes: bool,
+    ) -> anyhow::Result<()> {
+        let entries = self.config_entries(resource).await?;
+
+        let mut dynamic = BTreeMap::new();
+
+        for entry in entries
+            .iter()
+            .filter(|entry| resource.is_own_dynamic(entry))
+        {
+            let value = match (&entry.value, entry.is_sensitive) {
+                (Some(value), false) => value.clone(),
+                _ => bail!(
+                    "The sensitive entry {} of {} cannot be preserved, alter it with another tool",
+                    entry.name,
+                    resource
+                ),
+            };
+
+            dynamic.insert(entry.name.clone(), value);
+        }
+
+        let mut changes = Vec::new();
+
+        for (name, value) in set {
+            let entry = entries
+                .iter()
+                .find(|entry| entry.name == *name)
+                .with_context(|| format!("Unknown configuration {} for {}", name, resource))?;
+
+            if entry.is_read_only {
+                bail!("The configuration {} is read only", name);
+            }
+
+            if entry.value.as_ref() != Some(value) {
+                changes.push(ConfigChange {
+                    name: name.clone(),
+                    current: entry.value.clone(),
+                    new: Some(value.clone()),
+                });
+            }
+
+            dynamic.insert(name.clone(), value.clone());
+        }
+
+        for name in reset {
+            if dynamic.remove(name).is_none() {
+                eprintln!("{} is not set on {}, nothing to reset", name, resource);
+                continue;
+            }
+
+            changes.push(ConfigChange {
+                name: name.clone(),
+                current: entries
+                    .iter()
+                    .find(|entry| entry.name == *name)
+                    .and_then(|entry| entry.value.clone()),
+                new: None,
+            });
+        }
+
+        if changes.is_empty() {
+            println!("No changes to apply");
+            return Ok(());
+        }
+
+        println!("Changes to {}:", resource);
+        print_changes(&changes);
+
+        if dry_run || (!yes && !terminal::confirm("Apply these changes?")?) {
+            return Ok(());
+        }
+
+        let mut alter = AlterConfig::new(resource.specifier());
+
+        for (name, value) in &dynamic {
+            alter = alter.set(name, value);
+        }
+
+        let options = AdminOptions::new().operation_timeout(Some(REQUEST_TIMEOUT));
+        let results = self.admin()?.alter_configs([&alter], &options).await?;
+
+        for result in results {
+            if let Err((_, code)) = result {
+                bail!(
+                    "Could not alter the configuration of {}: {}",
+                    resource,
+                    code
+                );
+            }
+        }
+
+        println!("Configuration of {} updated", resource);
+
+        Ok(())
+    }
+
+    async fn config_entries(&self, resource: &ConfigResource) -> anyhow::Result<Vec<ConfigEntry>> {
+        let options = AdminOptions::new().request_timeout(Some(REQUEST_TIMEOUT));
+        let results = self
+            .admin()?
+            .describe_configs([&resource.specifier()], &options)
+            .await?;
+
+        let config = results
+            .into_iter()
+            .next()
+            .context("No configuration returned")?
+            .map_err(|code| {
+                anyhow::anyhow!(
+                    "Could not describe the configuration of {}: {}",
+                    resource,
+                    code
+                )
+            })?;
+
+        let mut entries = config.entries;
+        entries.sort_by(|a, b| a.name.cmp(&b.name));
+
+        Ok(entries)
+    }
+}
+
+fn source_name(source: &ConfigSource) -> &'static str {
+    match source {
+        ConfigSource::Unknown => "unknown",
+        ConfigSource::DynamicTopic => "dynamic topic",
+        ConfigSource::DynamicBroker => "dynamic broker",
+        ConfigSource::DynamicDefaultBroker => "dynamic default broker",
+        ConfigSource::StaticBroker => "static broker",
+        ConfigSource::Default => "default",
+    }
+}
+
+fn print_changes(changes: &[ConfigChange]) {
+    for change in changes {
+        let current = change.current.as_deref().unwrap_or("-");
+
+        match &change.new {
+            Some(new) => println!(
+                "  {}: {} -> {}",
+                change.name,
+                console::style(current).red(),
+                console::style(new).green()
+            ),
+            None => println!(
+                "  {}: {} -> {}",
+                change.name,
+                console::style(current).red(),
+                console::style("(default)").green()
+            ),
+        }
+    }
+}
@@ -0,0 +1,206 @@
+use anyhow::Context;
+use futures::stream::FuturesUnordered;
+use futures::StreamExt;
+use rdkafka::message::{Headers, OwnedMessage};
+use rdkafka::Message;
+
+use crate::cli::{CodecKind, RawFormat};
+use crate::kafka::client::{
+    DeliveryReport, EncodedRecord, KafkaClient, ProduceSummary, StartPosition,
+};
+use crate::schema_registry::{ProtoSelector, SchemaRegistryClient};
+
+/// Options controlling which messages [`KafkaClient::copy_to`] reads and how
+/// they are written to the destination.
+pub struct CopyOptions {
+    pub start: StartPosition,
+    /// Partitions to copy, all of the topic partitions when empty
+    pub partitions: Vec<i32>,
+    /// Stop each partition after the message at this offset
+    pub until_offset: Option<i64>,
+    /// Stop each partition at the first message after this timestamp (in milliseconds)
+    pub until_timestamp: Option<i64>,
+    /// Stop after copying this many messages
+    pub max_messages: Option<usize>,
+    /// Write each message to the partition it was read from, instead of letting
+    /// the destination partitioner choose
+    pub preserve_partition: bool,
+    pub decoding: CodecKind,
+    pub encoding: CodecKind,
+    pub key_decoding: CodecKind,
+    pub key_encoding: CodecKind,
+    /// Selects the schema used to encode protobuf values in the destination
+    pub proto: ProtoSelector,
+    pub max_in_flight: usize,
+}
+
+impl KafkaClient {
+    /// Copy the messages of `topic` to `to_topic` in the cluster of the destination
+    /// client, stopping once every partition reaches the end it had when the copy
+    /// started.
+    ///
+    /// Keys, headers and timestamps are preserved. Values are copied as they are
+    /// when both codecs are raw, otherwise they are decoded with the schema
+    /// registry of the source and encoded with the one of the destination.
+    pub async fn copy_to(
+        &self,
+        destination: &KafkaClient,
+        topic: &str,
+        to_topic: &str,
+        options: &CopyOptions,
+    ) -> anyhow::Result<ProduceSummary> {
+        let partitions = match options.partitions.as_slice() {
+            [] => Self::topic_partitions(&self.consumer, topic)?,
+            partitions => partitions.to_vec(),
+        };
+
+        let mut end_offsets =
+            self.assign_range(topic, &partitions, options.start, options.until_offset)?;
+
+        let mut in_flight = FuturesUnordered::new();
+        let mut summary = ProduceSummary::default();
+        let mut copied = 0;
+        let mut reading = options.max_messages.is_none_or(|max| max > 0);
+
+        // Deliveries keep progressing while waiting for the next message
+        loop {
+            tokio::select! {
+                Some(result) = in_flight.next(), if !in_flight.is_empty() => {
+                    summary.record(result, None)?;
+                }
+                message = self.next_in_range(topic, &mut end_offsets, options.until_timestamp),
+                    if reading && in_flight.len() < options.max_in_flight.max(1) =>
+                {
+                    let Some(message) = message? else {
+                        reading = false;
+                        continue;
+                    };
+
+                    in_flight.push(self.copy_message(
+                        destination,
+                        to_topic,
+                        message.detach(),
+                        options,
+                    ));
+                    copied += 1;
+                    reading = options.max_messages.is_none_or(|max| copied < max);
+                }
+                else => break,
+            }
+        }
+
+        Ok(summary)
+    }
+
+    async fn copy_message(
+        &self,
+        destination: &KafkaClient,
+        to_topic: &str,
+        message: OwnedMessage,
+        options: &CopyOptions,
+    ) -> anyhow::Result<DeliveryReport> {
+        let position = format!(
+            "Partition {} offset {}",
+            message.partition(),
+            message.offset()
+        );
+
+        let key_codecs = Transcoding {
+            source: &self.schema_registry,
+            destination: &destination.schema_registry,
+            decoding: options.key_decoding,
+            encoding: options.key_encoding,
+            proto: &ProtoSelector::default(),
+        };
+
+        let value_codecs = Transcoding {
+            decoding: options.decoding,
+            encoding: options.encoding,
+            proto: &options.proto,
+            ..key_codecs
+        };
+
+        let key = match message.key() {
+            Some(key) => Some(
+                key_codecs
+                    .transcode(to_topic, true, key)
+                    .await
+                    .with_context(|| format!("{}: could not re-encode the key", position))?,
+            ),
+            None => None,
+        };
+
+        let value = match message.payload() {
+            Some(value) => Some(
+                value_codecs
+                    .transcode(to_topic, false, value)
+                    .await
+                    .with_context(|| format!("{}: could not re-encode the message", position))?,
+            ),
+            None => None,
+        };
+
+        let headers = match message.headers() {
+            Some(headers) => headers
+                .iter()
+                .map(|header| (header.key.to_string(), header.value.map(<[u8]>::to_vec)))
+                .collect(),
+            None => Vec::new(),
+        };
+
+        let record = EncodedRecord {
+            key,
+            value,
+            headers,
+            partition: options.preserve_partition.then_some(message.partition()),
+            timestamp: message.timestamp().to_millis(),
+        };
+
+        let (partition, offset) = destination
+            .send(to_topic, &record)
+            .await
+            .with_context(|| format!("{}: failed to produce message", position))?;
+
+        Ok(DeliveryReport {
+            line: None,
+            topic: to_topic.to_string(),
+            partition,
+            offset,
+        })
+    }
+}
+
+/// How keys or values are decoded from the source and encoded for the
+/// destination.
+#[derive(Clone, Copy)]
+struct Transcoding<'a> {
+    source: &'a SchemaRegistryClient,
+    destination: &'a SchemaRegistryClient,
+    decoding: CodecKind,
+    encoding: CodecKind,
+    proto: &'a ProtoSelector,
+}
+
+impl Transcoding<'_> {
+    /// Decode a key or value with the source schema registry and encode it with
+    /// the destination one.
+    async fn transcode(&self, topic: &str, is_key: bool, bytes: &[u8]) -> anyhow::Result<Vec<u8>> {
+        // Raw payloads are handed to the encoder as they are, others go through JSON
+        let payload = match self.decoding {
+            CodecKind::Raw => bytes.to_vec(),
+            _ => {
+                let json = self
+                    .source
+                    .decode(self.decoding, RawFormat::Json, Some(bytes))
+                    .await?
+                    .unwrap_or_default();
+
+                serde_json::to_vec(&json)?
+            }
+        };
+
+        self.destination
+            .encode(self.encoding, topic, is_key, &payload, self.proto)
+            .await
+    }
+}
@@ -0,0 +1,417 @@
+use std::collections::{BTreeMap, HashMap};
+
+use anyhow::{bail, Context};
+use futures::stream::FuturesUnordered;
+use futures::StreamExt;
+use rdkafka::message::{Headers, OwnedMessage};
+use rdkafka::Message;
+
+use crate::cli::{HeaderFilter, HeaderOperator};
+use crate::kafka::client::{
+    DeliveryReport, EncodedRecord, KafkaClient, ProduceSummary, StartPosition,
+};
+use crate::terminal;
+
+/// Options controlling which dead-lettered messages [`KafkaClient::replay_dlq`]
+/// selects and where they are republished.
+pub struct ReplayOptions {
+    /// Partitions of the DLQ to read, all of them when empty
+    pub partitions: Vec<i32>,
+    /// Stop after reading this many messages
+    pub max_messages: Option<usize>,
+    /// Headers the summary groups the messages by, headers that look like error
+    /// details when empty
+    pub summary_headers: Vec<String>,
+    /// Only replay messages matching every filter
+    pub filters: Vec<HeaderFilter>,
+    /// The header holding the topic the message was dead-lettered from
+    pub topic_header: Option<String>,
+    /// Republish every message to this topic instead of the one in `topic_header`
+    pub to_topic: Option<String>,
+    /// The header counting how many times the message was replayed
+    pub replay_header: String,
+    /// Skip messages already replayed this many times
+    pub max_replays: Option<u32>,
+    pub max_in_flight: usize,
+}
+
+/// Outcome of a replay.
+#[derive(Debug, Default)]
+pub struct ReplaySummary {
+    pub selected: usize,
+    /// Messages matching the filters that could not be replayed, because their
+    /// original topic is unknown or they reached the maximum replays
+    pub skipped: usize,
+    pub produced: ProduceSummary,
+    read: usize,
+    /// Number of messages sharing each combination of error header values
+    errors: BTreeMap<String, usize>,
+}
+
+/// A message selected to be replayed.
+struct Replay {
+    message: OwnedMessage,
+    topic: String,
+    count: u32,
+}
+
+/// How a dead-lettered message is handled.
+enum Selection {
+    /// The message does not match the filters
+    Filtered,
+    /// The message matches the filters but its original topic is unknown or it
+    /// reached the maximum replays
+    Skipped,
+    /// The message is republished to `topic`, with `count` as its replay count
+    Replay { topic: String, count: u32 },
+}
+
+/// What [`KafkaClient::scan_dlq`] does with the selected messages.
+#[derive(Clone, Copy)]
+enum ScanAction {
+    Count,
+    List,
+    Replay,
+}
+
+impl KafkaClient {
+    /// Read the DLQ up to its current end, print a summary of the error headers and
+    /// republish the messages matching the filters to their original topic.
+    ///
+    /// Messages are streamed rather than held in memory, so confirming the replay
+    /// takes reading the DLQ twice: once for the summary and once to replay. Nothing
+    /// is produced on dry runs, the selected messages are listed instead.
+    pub async fn replay_dlq(
+        &self,
+        topic: &str,
+        options: &ReplayOptions,
+        dry_run: bool,
+        yes: bool,
+    ) -> anyhow::Result<ReplaySummary> {
+        let partitions = match options.partitions.as_slice() {
+            [] => Self::topic_partitions(&self.consumer, topic)?,
+            partitions => partitions.to_vec(),
+        };
+
+        let end_offsets = self.assign_range(topic, &partitions, StartPosition::Beginning, None)?;
+
+        if dry_run || yes {
+            let action = if dry_run {
+                ScanAction::List
+            } else {
+                ScanAction::Replay
+            };
+
+            let summary = self.scan_dlq(topic, options, end_offsets, action).await?;
+            print_error_summary(&summary);
+
+            return Ok(summary);
+        }
+
+        let scan = self
+            .scan_dlq(topic, options, end_offsets.clone(), ScanAction::Count)
+            .await?;
+        print_error_summary(&scan);
+
+        if scan.selected == 0 {
+            return Ok(scan);
+        }
+
+        let prompt = format!(
+            "Replay {} messages, skipping {}?",
+            scan.selected, scan.skipped
+        );
+
+        if !terminal::confirm(&prompt)? {
+            bail!("Aborted");
+        }
+
+        // The second read stops at the same offsets, ignoring messages dead-lettered since
+        self.assign_range(topic, &partitions, StartPosition::Beginning, None)?;
+        self.scan_dlq(topic, options, end_offsets, ScanAction::Replay)
+            .await
+    }
+
+    /// Read the assigned DLQ partitions up to `end_offsets`, counting the error
+    /// headers and selecting the messages to replay as they arrive.
+    async fn scan_dlq(
+        &self,
+        topic: &str,
+        options: &ReplayOptions,
+        mut end_offsets: HashMap<i32, i64>,
+        action: ScanAction,
+    ) -> anyhow::Result<ReplaySummary> {
+        let mut summary = ReplaySummary::default();
+        let mut in_flight = FuturesUnordered::new();
+        let mut reading = options.max_messages.is_none_or(|max| max > 0);
+
+        loop {
+            tokio::select! {
+                Some(result) = in_flight.next(), if !in_flight.is_empty() => {
+                    summary.produced.record(result, None)?;
+                }
+                message = self.next_in_range(topic, &mut end_offsets, None),
+                    if reading && in_flight.len() < options.max_in_flight.max(1) =>
+                {
+                    let Some(message) = message? else {
+                        reading = false;
+                        continue;
+                    };
+
+                    summary.read += 1;
+                    reading = options.max_messages.is_none_or(|max| summary.read < max);
+
+                    let headers = headers(&message);
+                    let group = error_group(&headers, &options.summary_headers);
+                    *summary.errors.entry(group).or_default() += 1;
+
+                    let (target, count) = match select(&headers, options) {
+                        Selection::Filtered => continue,
+                        Selection::Skipped => {
+                            summary.skipped += 1;
+                            continue;
+                        }
+                        Selection::Replay { topic, count } => (topic, count),
+                    };
+
+                    summary.selected += 1;
+
+                    match action {
+                        ScanAction::Count => {}
+                        ScanAction::List => println!(
+                            "Partition {} offset {}: replay {} to {}",
+                            message.partition(),
+                            message.offset(),
+                            count,
+                            target
+                        ),
+                        ScanAction::Replay => {
+                            let replay = Replay {
+                                message: message.detach(),
+                                topic: target,
+                                count,
+                            };
+
+                            in_flight.push(self.replay_message(replay, &options.replay_header));
+                        }
+                    }
+                }
+                else => break,
+            }
+        }
+
+        Ok(summary)
+    }
+
+    /// Republish a message as it is, counting the replay in its headers.
+    async fn replay_message(
+        &self,
+        replay: Replay,
+        replay_header: &str,
+    ) -> anyhow::Result<DeliveryReport> {
+        // Headers are copied as bytes, since they are not necessarily text
+        let mut headers = match replay.message.headers() {
+            Some(headers) => headers
+                .iter()
+                .filter(|header| header.key != replay_header)
+                .map(|header| (header.key.to_string(), header.value.map(<[u8]>::to_vec)))
+                .collect(),
+            None => Vec::new(),
+        };
+
+        headers.push((
+            replay_header.to_string(),
+            Some(replay.count.to_string().into_bytes()),
+        ));
+
+        let record = EncodedRecord {
+            key: replay.message.key().map(<[u8]>::to_vec),
+            value: replay.message.payload().map(<[u8]>::to_vec),
+            headers,
+            partition: None,
+            timestamp: None,
+        };
+
+        let (partition, offset) = self.send(&replay.topic, &record).await.with_context(|| {
+            format!(
+                "Partition {} offset {}: failed to replay message",
+                replay.message.partition(),
+                replay.message.offset()
+            )
+        })?;
+
+        Ok(DeliveryReport {
+            line: None,
+            topic: replay.topic,
+            partition,
+            offset,
+        })
+    }
+}
+
+/// Whether a message matching the filters is replayed, and where to.
+fn select(headers: &[(String, Option<String>)], options: &ReplayOptions) -> Selection {
+    if !options
+        .filters
+        .iter()
+        .all(|filter| matches(filter, headers))
+    {
+        return Selection::Filtered;
+    }
+
+    let target = options.to_topic.clone().or_else(|| {
+        let header = options.topic_header.as_ref()?;
+        headers.iter().find(|(key, _)| key == header)?.1.clone()
+    });
+
+    let count = headers
+        .iter()
+        .find(|(key, _)| *key == options.replay_header)
+        .and_then(|(_, value)| value.as_ref()?.parse().ok())
+        .unwrap_or(0);
+
+    let exhausted = options.max_replays.is_some_and(|max| count >= max);
+
+    match target {
+        Some(topic) if !exhausted => Selection::Replay {
+            topic,
+            count: count + 1,
+        },
+        _ => Selection::Skipped,
+    }
+}
+
+/// The combination of error header values a message is grouped by in the summary.
+fn error_group(headers: &[(String, Option<String>)], summary_headers: &[String]) -> String {
+    headers
+        .iter()
+        .filter(|(key, value)| match summary_headers {
+            [] => looks_like_error(key, value.as_deref()),
+            summary_headers => summary_headers.contains(key),
+        })
+        .map(|(key, value)| format!("{}={}", key, value.as_deref().unwrap_or_default()))
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
+/// Print how many messages share each combination of error header values.
+fn print_error_summary(summary: &ReplaySummary) {
+    let mut groups = summary.errors.iter().collect::<Vec<_>>();
+    groups.sort_by(|a, b| b.1.cmp(a.1).then_with(|| a.0.cmp(b.0)));
+
+    let rows = groups
+        .into_iter()
+        .map(|(group, count)| [count.to_string(), group.clone()])
+        .collect::<Vec<_>>();
+
+    println!("Read {} messages", summary.read);
+    println!();
+    terminal::print_table(["COUNT", "HEADERS"], &rows);
+    println!();
+}
+
+/// Whether the headers satisfy the filter, checking the first header with the
+/// filtered name. A missing header never matches.
+fn matches(filter: &HeaderFilter, headers: &[(String, Option<String>)]) -> bool {
+    let Some((_, value)) = headers.iter().find(|(key, _)| *key == filter.key) else {
+        return false;
+    };
+
+    let value = value.as_deref().unwrap_or_default();
+
+    match filter.operator {
+        HeaderOperator::Equals => value == filter.value,
+        HeaderOperator::NotEquals => value != filter.value,
+        HeaderOperator::Contains => value.contains(&filter.value),
+    }
+}
+
+/// Whether a header is likely to describe the error, leaving out the numeric
+/// ones such as the original offset, which would make every message unique.
+fn looks_like_error(key: &str, value: Option<&str>) -> bool {
+    let key = key.to_lowercase();
+    let numeric = value.is_some_and(|value| value.bytes().all(|c| c.is_ascii_digit()));
+
+    (key.contains("error") || key.contains("exception")) && !numeric
+}
+
+/// The headers of a message in order, with values read as text.
+fn headers(message: &impl Message) -> Vec<(String, Option<String>)> {
+    let Some(headers) = message.headers() else {
+        return Vec::new();
+    };
+
+    headers
+        .iter()
+        .map(|header| {
+            let value = header
+                .value
+                .map(|value| String::from_utf8_lossy(value).to_string());
+
+            (header.key.to_string(), value)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::parse_header_filter;
+
+    fn header_list(headers: &[(&str, Option<&str>)]) -> Vec<(String, Option<String>)> {
+        headers
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.map(str::to_string)))
+            .collect()
+    }
+
+    fn filter(filter: &str) -> HeaderFilter {
+        parse_header_filter(filter).unwrap()
+    }
+
+    #[test]
+    fn equals() {
+        let headers = header_list(&[("error", Some("timeout")), ("source", None)]);
+
+        assert!(matches(&filter("error=timeout"), &headers));
+        assert!(!matches(&filter("error=time"), &headers));
+        assert!(matches(&filter("source="), &headers));
+    }
+
+    #[test]
+    fn not_equals() {
+        let headers = header_list(&[("error", Some("timeout"))]);
+
+        assert!(matches(&filter("error!=refused"), &headers));
+        assert!(!matches(&filter("error!=timeout"), &headers));
+    }
+
+    #[test]
+    fn contains() {
+        let headers = header_list(&[("error", Some("connection timeout"))]);
+
+        assert!(matches(&filter("error~timeout"), &headers));
+        assert!(matches(&filter("error~"), &headers));
+        assert!(!matches(&filter("error~refused"), &headers));
+    }
+
+    #[test]
+    fn missing_headers_never_match() {
+        let headers = header_list(&[("other", Some("timeout"))]);
+
+        assert!(!matches(&filter("error=timeout"), &headers));
+        assert!(!matches(&filter("error!=timeout"), &headers));
+        assert!(!matches(&filter("error~timeout"), &headers));
+        assert!(!matches(&filter("error=timeout"), &[]));
+    }
+
+    #[test]
+    fn duplicate_headers_match_the_first_one() {
+        let headers = header_list(&[("error", Some("timeout")), ("error", Some("refused"))]);
+
+        assert!(matches(&filter("error=timeout"), &headers));
+        assert!(!matches(&filter("error=refused"), &headers));
+        assert!(matches(&filter("error!=refused"), &headers));
+        assert!(!matches(&filter("error~refused"), &headers));
+    }
+}
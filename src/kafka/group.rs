@@ -0,0 +1,742 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+
+use anyhow::{bail, Context};
+use rdkafka::admin::AdminOptions;
+use rdkafka::consumer::{BaseConsumer, CommitMode, Consumer};
+use rdkafka::{Offset, TopicPartitionList};
+use serde::{Deserialize, Serialize};
+
+use crate::cli::OutputFormat;
+use crate::kafka::client::{KafkaClient, REQUEST_TIMEOUT};
+use crate::terminal;
+
+#[derive(Debug, Serialize)]
+pub struct GroupSummary {
+    pub name: String,
+    pub state: String,
+    pub protocol_type: String,
+    pub members: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct GroupDescription {
+    pub name: String,
+    pub state: String,
+    pub protocol_type: String,
+    pub protocol: String,
+    pub members: Vec<GroupMember>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct GroupMember {
+    pub id: String,
+    pub client_id: String,
+    pub client_host: String,
+    /// Assigned partitions by topic
+    pub assignment: BTreeMap<String, Vec<i32>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct GroupLag {
+    pub group: String,
+    pub partitions: Vec<PartitionLag>,
+    /// Lag of each topic, summing the partitions with a committed offset
+    pub topics: BTreeMap<String, i64>,
+    pub total: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PartitionLag {
+    pub topic: String,
+    pub partition: i32,
+    /// Missing when the group has not committed an offset for the partition
+    pub committed: Option<i64>,
+    pub high: i64,
+    pub lag: Option<i64>,
+}
+
+/// Where the offsets of a group are moved to.
+#[derive(Debug)]
+pub enum OffsetReset {
+    Earliest,
+    Latest,
+    Offset(i64),
+    /// Move the committed offsets forward (or backward when negative)
+    ShiftBy(i64),
+    /// The first message at or after this time, in milliseconds since the epoch
+    Timestamp(i64),
+    /// The offsets listed in a CSV or JSON file
+    File(Vec<PartitionOffset>),
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PartitionOffset {
+    pub topic: String,
+    pub partition: i32,
+    /// Also read from `after`, so a reset preview printed as JSON can be replayed
+    #[serde(alias = "after")]
+    pub offset: i64,
+}
+
+impl PartitionOffset {
+    /// Read the offsets from a JSON array or from CSV lines of `topic,partition,offset`,
+    /// with an optional header.
+    pub fn read_file(path: &Path) -> anyhow::Result<Vec<PartitionOffset>> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Could not read {}", path.display()))?;
+
+        if path
+            .extension()
+            .is_some_and(|extension| extension == "json")
+        {
+            return serde_json::from_str(&text)
+                .with_context(|| format!("Invalid offsets file {}", path.display()));
+        }
+
+        let mut offsets = Vec::new();
+
+        for (idx, line) in text.lines().enumerate() {
+            let line = line.trim();
+
+            if line.is_empty() || (idx == 0 && line.starts_with("topic")) {
+                continue;
+            }
+
+            let invalid = || format!("Invalid line {}, expected topic,partition,offset", idx + 1);
+
+            let mut columns = line.rsplitn(3, ',');
+            let offset = columns.next().with_context(invalid)?;
+            let partition = columns.next().with_context(invalid)?;
+            let topic = columns.next().with_context(invalid)?;
+
+            offsets.push(PartitionOffset {
+                topic: topic.trim().to_string(),
+                partition: partition.trim().parse().with_context(invalid)?,
+                offset: offset.trim().parse().with_context(invalid)?,
+            });
+        }
+
+        Ok(offsets)
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct OffsetChange {
+    pub topic: String,
+    pub partition: i32,
+    /// Missing when the group has not committed an offset for the partition
+    pub before: Option<i64>,
+    pub after: i64,
+}
+
+impl KafkaClient {
+    pub fn list_groups(&self, format: OutputFormat) -> anyhow::Result<()> {
+        let group_list = self.consumer.fetch_group_list(None, REQUEST_TIMEOUT)?;
+
+        let mut groups = group_list
+            .groups()
+            .iter()
+            .map(|group| GroupSummary {
+                name: group.name().to_string(),
+                state: group.state().to_string(),
+                protocol_type: group.protocol_type().to_string(),
+                members: group.members().len(),
+            })
+            .collect::<Vec<GroupSummary>>();
+
+        groups.sort_by(|a, b| a.name.cmp(&b.name));
+
+        match format {
+            OutputFormat::Text => {
+                let rows = groups
+                    .iter()
+                    .map(|group| {
+                        [
+                            group.name.clone(),
+                            group.state.clone(),
+                            group.protocol_type.clone(),
+                            group.members.to_string(),
+                        ]
+                    })
+                    .collect::<Vec<_>>();
+
+                terminal::print_table(["GROUP", "STATE", "TYPE", "MEMBERS"], &rows);
+            }
+            OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&groups)?),
+        }
+
+        Ok(())
+    }
+
+    pub fn describe_group(&self, group: &str, format: OutputFormat) -> anyhow::Result<()> {
+        let description = self.group_description(group)?;
+
+        match format {
+            OutputFormat::Text => {
+                println!("Group:    {}", description.name);
+                println!("State:    {}", description.state);
+                println!(
+                    "Protocol: {} ({})",
+                    description.protocol, description.protocol_type
+                );
+                println!();
+
+                let rows = description
+                    .members
+                    .iter()
+                    .map(|member| {
+                        let assignment = member
+                            .assignment
+                            .iter()
+                            .map(|(topic, partitions)| {
+                                let partitions = partitions
+                                    .iter()
+                                    .map(|p| p.to_string())
+                                    .collect::<Vec<String>>()
+                                    .join(",");
+
+                                format!("{}:{}", topic, partitions)
+                            })
+                            .collect::<Vec<String>>()
+                            .join(" ");
+
+                        [
+                            member.id.clone(),
+                            member.client_id.clone(),
+                            member.client_host.clone(),
+                            assignment,
+                        ]
+                    })
+                    .collect::<Vec<_>>();
+
+                terminal::print_table(["MEMBER", "CLIENT-ID", "HOST", "ASSIGNMENT"], &rows);
+            }
+            OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&description)?),
+        }
+
+        Ok(())
+    }
+
+    pub fn group_lag(
+        &self,
+        group: &str,
+        topic: Option<&str>,
+        format: OutputFormat,
+    ) -> anyhow::Result<()> {
+        let lag = self.compute_group_lag(group, topic)?;
+
+        match format {
+            OutputFormat::Text => {
+                let optional = |value: Option<i64>| value.map_or("-".into(), |v| v.to_string());
+
+                let rows = lag
+                    .partitions
+                    .iter()
+                    .map(|p| {
+                        [
+                            p.topic.clone(),
+                            p.partition.to_string(),
+                            optional(p.committed),
+                            p.high.to_string(),
+                            optional(p.lag),
+                        ]
+                    })
+                    .collect::<Vec<_>>();
+
+                terminal::print_table(["TOPIC", "PARTITION", "COMMITTED", "HIGH", "LAG"], &rows);
+                println!();
+
+                for (topic, topic_lag) in &lag.topics {
+                    println!("Lag of {}: {}", topic, topic_lag);
+                }
+
+                println!("Total lag: {}", lag.total);
+            }
+            OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&lag)?),
+        }
+
+        Ok(())
+    }
+
+    /// Preview the new offsets of the group, committing them only when `execute` is set.
+    pub fn reset_offsets(
+        &self,
+        group: &str,
+        topic: Option<&str>,
+        partitions: &[i32],
+        reset: &OffsetReset,
+        execute: bool,
+        format: OutputFormat,
+    ) -> anyhow::Result<()> {
+        let description = self.group_description(group)?;
+
+        if !description.members.is_empty() {
+            bail!(
+                "Group {} has {} active members, stop its consumers before resetting the offsets",
+                group,
+                description.members.len()
+            );
+        }
+
+        let targets = self.reset_targets(topic, partitions, reset)?;
+        let consumer = self.group_consumer(group)?;
+        let committed = consumer.committed_offsets(targets, REQUEST_TIMEOUT)?;
+
+        let mut lookup = TopicPartitionList::new();
+
+        if let OffsetReset::Timestamp(timestamp) = reset {
+            for element in committed.elements() {
+                lookup.add_partition_offset(
+                    element.topic(),
+                    element.partition(),
+                    Offset::Offset(*timestamp),
+                )?;
+            }
+
+            lookup = consumer.offsets_for_times(lookup, REQUEST_TIMEOUT)?;
+        }
+
+        let mut changes = Vec::new();
+
+        for element in committed.elements() {
+            let (topic, partition) = (element.topic(), element.partition());
+            let (low, high) = self
+                .consumer
+                .fetch_watermarks(topic, partition, REQUEST_TIMEOUT)?;
+
+            let before = match element.offset() {
+                Offset::Offset(offset) => Some(offset),
+                _ => None,
+            };
+
+            let after = match reset {
+                OffsetReset::Earliest => low,
+                OffsetReset::Latest => high,
+                OffsetReset::Offset(offset) => *offset,
+                OffsetReset::ShiftBy(shift) => {
+                    let before = before.with_context(|| {
+                        format!(
+                            "Group {} has no committed offset to shift on {}/{}",
+                            group, topic, partition
+                        )
+                    })?;
+
+                    before + shift
+                }
+                // Partitions without messages after the timestamp move to the end
+                OffsetReset::Timestamp(_) => match lookup
+                    .find_partition(topic, partition)
+                    .map(|element| element.offset())
+                {
+                    Some(Offset::Offset(offset)) => offset,
+                    _ => high,
+                },
+                OffsetReset::File(offsets) => offsets
+                    .iter()
+                    .find(|o| o.topic == topic && o.partition == partition)
+                    .map(|o| o.offset)
+                    .unwrap_or(high),
+            };
+
+            changes.push(OffsetChange {
+                topic: topic.to_string(),
+                partition,
+                before,
+                after: after.clamp(low, high),
+            });
+        }
+
+        changes.sort_by(|a, b| (&a.topic, a.partition).cmp(&(&b.topic, b.partition)));
+
+        match format {
+            OutputFormat::Text => {
+                let rows = changes
+                    .iter()
+                    .map(|change| {
+                        [
+                            change.topic.clone(),
+                            change.partition.to_string(),
+                            change.before.map_or("-".into(), |v| v.to_string()),
+                            change.after.to_string(),
+                        ]
+                    })
+                    .collect::<Vec<_>>();
+
+                terminal::print_table(["TOPIC", "PARTITION", "BEFORE", "AFTER"], &rows);
+            }
+            OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&changes)?),
+        }
+
+        if !execute {
+            eprintln!("Dry run, use --execute to commit the new offsets");
+            return Ok(());
+        }
+
+        let mut offsets = TopicPartitionList::new();
+
+        for change in &changes {
+            offsets.add_partition_offset(
+                &change.topic,
+                change.partition,
+                Offset::Offset(change.after),
+            )?;
+        }
+
+        consumer
+            .commit(&offsets, CommitMode::Sync)
+            .with_context(|| format!("Could not commit the offsets of group {}", group))?;
+
+        eprintln!("Committed the offsets of {} partitions", changes.len());
+
+        Ok(())
+    }
+
+    pub async fn delete_groups(&self, groups: &[String]) -> anyhow::Result<()> {
+        let groups = groups.iter().map(String::as_str).collect::<Vec<&str>>();
+
+        let options = AdminOptions::new().operation_timeout(Some(REQUEST_TIMEOUT));
+        let results = self.admin()?.delete_groups(&groups, &options).await?;
+
+        let mut failed = false;
+
+        for result in results {
+            match result {
+                Ok(group) => println!("Deleted group {}", group),
+                Err((group, code)) => {
+                    eprintln!("Could not delete group {}: {}", group, code);
+                    failed = true;
+                }
+            }
+        }
+
+        if failed {
+            bail!("Some groups could not be deleted");
+        }
+
+        Ok(())
+    }
+
+    /// The partitions whose offsets are reset: the ones listed in the offsets file or
+    /// the partitions of the topic, narrowed down to `partitions` when given.
+    fn reset_targets(
+        &self,
+        topic: Option<&str>,
+        partitions: &[i32],
+        reset: &OffsetReset,
+    ) -> anyhow::Result<TopicPartitionList> {
+        let mut targets = TopicPartitionList::new();
+
+        let included = |t: &str, p: i32| {
+            topic.is_none_or(|topic| topic == t)
+                && (partitions.is_empty() || partitions.contains(&p))
+        };
+
+        if let OffsetReset::File(offsets) = reset {
+            for offset in offsets {
+                if included(&offset.topic, offset.partition) {
+                    targets.add_partition(&offset.topic, offset.partition);
+                }
+            }
+        } else {
+            let topic = topic.context("A topic must be specified")?;
+            let topic_partitions = Self::topic_partitions(&self.consumer, topic)?;
+
+            if let Some(missing) = partitions.iter().find(|p| !topic_partitions.contains(p)) {
+                bail!("Topic {} has no partition {}", topic, missing);
+            }
+
+            for partition in topic_partitions {
+                if included(topic, partition) {
+                    targets.add_partition(topic, partition);
+                }
+            }
+        }
+
+        if targets.count() == 0 {
+            bail!("No partitions to reset");
+        }
+
+        Ok(targets)
+    }
+
+    pub(super) fn group_description(&self, group: &str) -> anyhow::Result<GroupDescription> {
+        let group_list = self
+            .consumer
+            .fetch_group_list(Some(group), REQUEST_TIMEOUT)?;
+
+        let info = group_list
+            .groups()
+            .iter()
+            .find(|info| info.name() == group)
+            .with_context(|| format!("Group {} not found", group))?;
+
+        let mut members = Vec::new();
+
+        for member in info.members() {
+            let assignment = match (info.protocol_type(), member.assignment()) {
+                ("consumer", Some(bytes)) if !bytes.is_empty() => decode_assignment(bytes)
+                    .with_context(|| format!("Invalid assignment of member {}", member.id()))?,
+                _ => BTreeMap::new(),
+            };
+
+            members.push(GroupMember {
+                id: member.id().to_string(),
+                client_id: member.client_id().to_string(),
+                client_host: member.client_host().to_string(),
+                assignment,
+            });
+        }
+
+        Ok(GroupDescription {
+            name: info.name().to_string(),
+            state: info.state().to_string(),
+            protocol_type: info.protocol_type().to_string(),
+            protocol: info.protocol().to_string(),
+            members,
+        })
+    }
+
+    /// Create a consumer using the group id, which is never subscribed so it
+    /// does not join the group, to look up and commit its offsets.
+    pub(super) fn group_consumer(&self, group: &str) -> anyhow::Result<BaseConsumer> {
+        let consumer = self
+            .config
+            .clone()
+            .set("group.id", group)
+            .set("enable.auto.commit", "false")
+            .create()?;
+
+        Ok(consumer)
+    }
+
+    /// The partitions the group may have committed offsets for: the given topic,
+    /// the topics assigned to its members, or every topic when the group is empty.
+    pub(super) fn group_partitions(
+        &self,
+        group: &str,
+        topic: Option<&str>,
+    ) -> anyhow::Result<TopicPartitionList> {
+        let topics = match topic {
+            Some(topic) => BTreeSet::from([topic.to_string()]),
+            None => self
+                .group_description(group)?
+                .members
+                .into_iter()
+                .flat_map(|member| member.assignment.into_keys())
+                .collect(),
+        };
+
+        let metadata = self.consumer.fetch_metadata(None, REQUEST_TIMEOUT)?;
+        let mut partitions = TopicPartitionList::new();
+
+        for topic_metadata in metadata.topics() {
+            let included = match topics.is_empty() {
+                true => !topic_metadata.name().starts_with("__"),
+                false => topics.contains(topic_metadata.name()),
+            };
+
+            if !included {
+                continue;
+            }
+
+            for partition in topic_metadata.partitions() {
+                partitions.add_partition(topic_metadata.name(), partition.id());
+            }
+        }
+
+        if let Some(topic) = topic {
+            if partitions.count() == 0 {
+                bail!("Topic {} not found", topic);
+            }
+        }
+
+        Ok(partitions)
+    }
+
+    fn compute_group_lag(&self, group: &str, topic: Option<&str>) -> anyhow::Result<GroupLag> {
+        let partitions = self.group_partitions(group, topic)?;
+        let committed = self
+            .group_consumer(group)?
+            .committed_offsets(partitions, REQUEST_TIMEOUT)?;
+
+        let mut lag = GroupLag {
+            group: group.to_string(),
+            partitions: Vec::new(),
+            topics: BTreeMap::new(),
+            total: 0,
+        };
+
+        for element in committed.elements() {
+            let committed = match element.offset() {
+                Offset::Offset(offset) => Some(offset),
+                _ => None,
+            };
+
+            // Partitions of other topics the group never committed to are noise
+            if committed.is_none() && topic.is_none() {
+                continue;
+            }
+
+            let (_, high) = self.consumer.fetch_watermarks(
+                element.topic(),
+                element.partition(),
+                REQUEST_TIMEOUT,
+            )?;
+
+            let partition_lag = committed.map(|committed| (high - committed).max(0));
+
+            if let Some(partition_lag) = partition_lag {
+                *lag.topics.entry(element.topic().to_string()).or_default() += partition_lag;
+                lag.total += partition_lag;
+            }
+
+            lag.partitions.push(PartitionLag {
+                topic: element.topic().to_string(),
+                partition: element.partition(),
+                committed,
+                high,
+                lag: partition_lag,
+            });
+        }
+
+        lag.partitions
+            .sort_by(|a, b| (&a.topic, a.partition).cmp(&(&b.topic, b.partition)));
+
+        Ok(lag)
+    }
+}
+
+/// Decode the partitions assigned to a member of a group using the consumer
+/// protocol, as sent by the broker.
+fn decode_assignment(bytes: &[u8]) -> anyhow::Result<BTreeMap<String, Vec<i32>>> {
+    let mut reader = bytes;
+
+    let _version = read_i16(&mut reader)?;
+    let topic_count = read_i32(&mut reader)?;
+
+    let mut assignment = BTreeMap::new();
+
+    for _ in 0..topic_count.max(0) {
+        let topic_len = read_i16(&mut reader)?.max(0) as usize;
+        let topic = take(&mut reader, topic_len)?;
+        let topic = String::from_utf8_lossy(topic).to_string();
+
+        let partition_count = read_i32(&mut reader)?;
+        let mut partitions = Vec::new();
+
+        for _ in 0..partition_count.max(0) {
+            partitions.push(read_i32(&mut reader)?);
+        }
+
+        partitions.sort();
+        assignment.insert(topic, partitions);
+    }
+
+    Ok(assignment)
+}
+
+fn take<'a>(reader: &mut &'a [u8], len: usize) -> anyhow::Result<&'a [u8]> {
+    if reader.len() < len {
+        bail!("Unexpected end of data");
+    }
+
+    let (bytes, rest) = reader.split_at(len);
+    *reader = rest;
+
+    Ok(bytes)
+}
+
+fn read_i16(reader: &mut &[u8]) -> anyhow::Result<i16> {
+    let bytes = take(reader, 2)?;
+    Ok(i16::from_be_bytes([bytes[0], bytes[1]]))
+}
+
+fn read_i32(reader: &mut &[u8]) -> anyhow::Result<i32> {
+    let bytes = take(reader, 4)?;
+    Ok(i32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Write the contents to a temporary file named after `name` and read it back.
+    fn read(name: &str, contents: &str) -> anyhow::Result<Vec<(String, i32, i64)>> {
+        let path = std::env::temp_dir().join(format!("ktools-{}-{}", std::process::id(), name));
+        std::fs::write(&path, contents).unwrap();
+
+        let offsets = PartitionOffset::read_file(&path);
+        std::fs::remove_file(&path).unwrap();
+
+        Ok(offsets?
+            .into_iter()
+            .map(|offset| (offset.topic, offset.partition, offset.offset))
+            .collect())
+    }
+
+    #[test]
+    fn csv_offsets() {
+        let offsets = read(
+            "offsets.csv",
+            "topic,partition,offset\norders,0,10\n\n orders.v2 , 1 , 20 \nname,with,commas,2,30\n",
+        )
+        .unwrap();
+
+        assert_eq!(
+            offsets,
+            [
+                ("orders".to_string(), 0, 10),
+                ("orders.v2".to_string(), 1, 20),
+                ("name,with,commas".to_string(), 2, 30),
+            ]
+        );
+    }
+
+    #[test]
+    fn csv_offsets_without_header() {
+        let offsets = read("headerless.csv", "orders,3,42").unwrap();
+
+        assert_eq!(offsets, [("orders".to_string(), 3, 42)]);
+    }
+
+    #[test]
+    fn json_offsets() {
+        let offsets = read(
+            "offsets.json",
+            r#"[
+                {"topic": "orders", "partition": 0, "offset": 10},
+                {"topic": "orders", "partition": 1, "before": 5, "after": 20}
+            ]"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            offsets,
+            [("orders".to_string(), 0, 10), ("orders".to_string(), 1, 20)]
+        );
+    }
+
+    #[test]
+    fn malformed_offsets_are_rejected() {
+        for (name, contents) in [
+            ("missing-column.csv", "orders,10"),
+            ("text-partition.csv", "orders,first,10"),
+            ("text-offset.csv", "orders,0,latest"),
+            ("late-header.csv", "orders,0,10\ntopic,partition,offset"),
+            (
+                "missing-field.json",
+                r#"[{"topic": "orders", "partition": 0}]"#,
+            ),
+            (
+                "not-an-array.json",
+                r#"{"topic": "orders", "partition": 0, "offset": 1}"#,
+            ),
+        ] {
+            assert!(read(name, contents).is_err(), "{} was accepted", name);
+        }
+
+        let err = read("second-line.csv", "orders,0,10\norders,1").unwrap_err();
+        assert!(err.to_string().contains("line 2"), "{}", err);
+    }
+}
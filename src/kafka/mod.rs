@@ -1,2 +1,9 @@
+pub mod acl;
 pub mod client;
+pub mod cluster;
 pub mod config;
+pub mod configs;
+pub mod copy;
+pub mod dlq;
+pub mod group;
+pub mod topic;
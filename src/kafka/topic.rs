@@ -0,0 +1,313 @@
+use anyhow::{bail, Context};
+use rdkafka::admin::{AdminOptions, NewTopic, TopicReplication};
+use rdkafka::consumer::Consumer;
+use rdkafka::{Offset, TopicPartitionList};
+use serde::Serialize;
+
+use crate::cli::OutputFormat;
+use crate::kafka::client::{KafkaClient, REQUEST_TIMEOUT};
+use crate::terminal;
+
+#[derive(Debug, Serialize)]
+pub struct TopicSummary {
+    pub name: String,
+    pub partitions: usize,
+    pub replication_factor: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TopicDescription {
+    pub name: String,
+    pub partitions: Vec<PartitionDescription>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PartitionDescription {
+    pub partition: i32,
+    /// The broker leading the partition, -1 when the partition is offline
+    pub leader: i32,
+    pub replicas: Vec<i32>,
+    pub isr: Vec<i32>,
+    /// Watermarks are missing when they could not be fetched
+    pub low: Option<i64>,
+    pub high: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TopicOffsets {
+    pub topic: String,
+    /// The time the offsets were looked up for, in milliseconds since the epoch
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub at: Option<i64>,
+    pub partitions: Vec<PartitionOffsets>,
+    pub messages: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PartitionOffsets {
+    pub partition: i32,
+    pub low: i64,
+    pub high: i64,
+    /// Approximate, compacted and transactional topics hold fewer messages
+    pub messages: i64,
+    /// The first offset at or after the requested time, the high watermark when
+    /// every message is older
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub offset_at: Option<i64>,
+}
+
+impl KafkaClient {
+    pub fn list_topics(&self, filter: Option<&str>, format: OutputFormat) -> anyhow::Result<()> {
+        let metadata = self.consumer.fetch_metadata(None, REQUEST_TIMEOUT)?;
+
+        let mut topics = metadata
+            .topics()
+            .iter()
+            .filter(|topic| filter.is_none_or(|filter| topic.name().contains(filter)))
+            .map(|topic| TopicSummary {
+                name: topic.name().to_string(),
+                partitions: topic.partitions().len(),
+                replication_factor: topic
+                    .partitions()
+                    .first()
+                    .map_or(0, |partition| partition.replicas().len()),
+            })
+            .collect::<Vec<TopicSummary>>();
+
+        topics.sort_by(|a, b| a.name.cmp(&b.name));
+
+        match format {
+            OutputFormat::Text => {
+                let rows = topics
+                    .iter()
+                    .map(|topic| {
+                        [
+                            topic.name.clone(),
+                            topic.partitions.to_string(),
+                            topic.replication_factor.to_string(),
+                        ]
+                    })
+                    .collect::<Vec<_>>();
+
+                terminal::print_table(["TOPIC", "PARTITIONS", "REPLICATION"], &rows);
+            }
+            OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&topics)?),
+        }
+
+        Ok(())
+    }
+
+    pub fn describe_topic(&self, topic: &str, format: OutputFormat) -> anyhow::Result<()> {
+        let description = self.topic_description(topic)?;
+
+        match format {
+            OutputFormat::Text => {
+                let rows = description
+                    .partitions
+                    .iter()
+                    .map(|p| {
+                        [
+                            p.partition.to_string(),
+                            p.leader.to_string(),
+                            join_ids(&p.replicas),
+                            join_ids(&p.isr),
+                            p.low.map_or("-".into(), |low| low.to_string()),
+                            p.high.map_or("-".into(), |high| high.to_string()),
+                        ]
+                    })
+                    .collect::<Vec<_>>();
+
+                terminal::print_table(
+                    ["PARTITION", "LEADER", "REPLICAS", "ISR", "LOW", "HIGH"],
+                    &rows,
+                );
+            }
+            OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&description)?),
+        }
+
+        Ok(())
+    }
+
+    pub fn topic_offsets(
+        &self,
+        topic: &str,
+        at: Option<i64>,
+        format: OutputFormat,
+    ) -> anyhow::Result<()> {
+        let partitions = Self::topic_partitions(&self.consumer, topic)?;
+
+        let offsets_at = match at {
+            Some(timestamp) => {
+                let mut lookup = TopicPartitionList::new();
+
+                for &partition in &partitions {
+                    lookup.add_partition_offset(topic, partition, Offset::Offset(timestamp))?;
+                }
+
+                Some(self.consumer.offsets_for_times(lookup, REQUEST_TIMEOUT)?)
+            }
+            None => None,
+        };
+
+        let mut offsets = TopicOffsets {
+            topic: topic.to_string(),
+            at,
+            partitions: Vec::new(),
+            messages: 0,
+        };
+
+        for partition in partitions {
+            let (low, high) = self
+                .consumer
+                .fetch_watermarks(topic, partition, REQUEST_TIMEOUT)?;
+
+            let offset_at = offsets_at.as_ref().map(|offsets_at| {
+                match offsets_at
+                    .find_partition(topic, partition)
+                    .map(|element| element.offset())
+                {
+                    Some(Offset::Offset(offset)) => offset,
+                    _ => high,
+                }
+            });
+
+            offsets.messages += high - low;
+            offsets.partitions.push(PartitionOffsets {
+                partition,
+                low,
+                high,
+                messages: high - low,
+                offset_at,
+            });
+        }
+
+        offsets.partitions.sort_by_key(|p| p.partition);
+
+        match format {
+            OutputFormat::Text => {
+                let rows = offsets
+                    .partitions
+                    .iter()
+                    .map(|p| {
+                        [
+                            p.partition.to_string(),
+                            p.low.to_string(),
+                            p.high.to_string(),
+                            p.messages.to_string(),
+                            p.offset_at.map_or("-".into(), |offset| offset.to_string()),
+                        ]
+                    })
+                    .collect::<Vec<_>>();
+
+                terminal::print_table(["PARTITION", "LOW", "HIGH", "MESSAGES", "AT"], &rows);
+                println!();
+                println!("Total messages: {}", offsets.messages);
+            }
+            OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&offsets)?),
+        }
+
+        Ok(())
+    }
+
+    pub(super) fn topic_description(&self, topic: &str) -> anyhow::Result<TopicDescription> {
+        let metadata = self.consumer.fetch_metadata(Some(topic), REQUEST_TIMEOUT)?;
+
+        let topic_metadata = metadata
+            .topics()
+            .iter()
+            .find(|t| t.name() == topic)
+            .with_context(|| format!("Topic {} not found", topic))?;
+
+        if let Some(err) = topic_metadata.error() {
+            bail!("Could not fetch the metadata of topic {}: {:?}", topic, err);
+        }
+
+        let mut partitions = Vec::new();
+
+        for partition in topic_metadata.partitions() {
+            let watermarks = self
+                .consumer
+                .fetch_watermarks(topic, partition.id(), REQUEST_TIMEOUT)
+                .ok();
+
+            partitions.push(PartitionDescription {
+                partition: partition.id(),
+                leader: partition.leader(),
+                replicas: partition.replicas().to_vec(),
+                isr: partition.isr().to_vec(),
+                low: watermarks.map(|(low, _)| low),
+                high: watermarks.map(|(_, high)| high),
+            });
+        }
+
+        partitions.sort_by_key(|p| p.partition);
+
+        Ok(TopicDescription {
+            name: topic.to_string(),
+            partitions,
+        })
+    }
+
+    pub async fn create_topic(
+        &self,
+        topic: &str,
+        partitions: i32,
+        replication_factor: i32,
+        configs: &[(String, String)],
+    ) -> anyhow::Result<()> {
+        let mut new_topic = NewTopic::new(
+            topic,
+            partitions,
+            TopicReplication::Fixed(replication_factor),
+        );
+
+        for (key, value) in configs {
+            new_topic = new_topic.set(key, value);
+        }
+
+        let options = AdminOptions::new().operation_timeout(Some(REQUEST_TIMEOUT));
+        let results = self.admin()?.create_topics([&new_topic], &options).await?;
+
+        for result in results {
+            if let Err((topic, code)) = result {
+                bail!("Could not create topic {}: {}", topic, code);
+            }
+        }
+
+        println!("Created topic {}", topic);
+
+        Ok(())
+    }
+
+    pub async fn delete_topics(&self, topics: &[String]) -> anyhow::Result<()> {
+        let topics = topics.iter().map(String::as_str).collect::<Vec<&str>>();
+
+        let options = AdminOptions::new().operation_timeout(Some(REQUEST_TIMEOUT));
+        let results = self.admin()?.delete_topics(&topics, &options).await?;
+
+        let mut failed = false;
+
+        for result in results {
+            match result {
+                Ok(topic) => println!("Deleted topic {}", topic),
+                Err((topic, code)) => {
+                    eprintln!("Could not delete topic {}: {}", topic, code);
+                    failed = true;
+                }
+            }
+        }
+
+        if failed {
+            bail!("Some topics could not be deleted");
+        }
+
+        Ok(())
+    }
+}
+
+pub(super) fn join_ids(ids: &[i32]) -> String {
+    ids.iter()
+        .map(|id| id.to_string())
+        .collect::<Vec<String>>()
+        .join(",")
+}
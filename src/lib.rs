@@ -1,23 +1,38 @@
 use std::fs;
+use std::time::Duration;
 
 use anyhow::{anyhow, bail, Context};
 use clap::Parser;
+use regex::Regex;
 
 
 
-use kafka::client::KafkaClient;
+use kafka::acl::{AclFilter, NewAcl};
+use kafka::client::{ConsumeOptions, KafkaClient, ProduceOptions, ProduceRecord, StartPosition};
+use kafka::configs::ConfigResource;
+use kafka::copy::CopyOptions;
+use kafka::dlq::ReplayOptions;
+use kafka::group::{OffsetReset, PartitionOffset};
 use serde_json::Value as JsonValue;
+use tokio::io::{AsyncBufRead, BufReader};
 
 use crate::cli::{
-    Command, ConfigCommand, KToolsCliArgs, KafkaCommand, Options, SchemaRegistryCommand,
+    AclCommand, AclFilterArgs, CodecKind, Command, CompatibilityCommand, ConfigCommand, CopyArgs,
+    DlqCommand, GroupCommand, KToolsCliArgs, KafkaCommand, KafkaConfigCommand, KafkaContextCommand,
+    Options, OutputFormat, SchemaRegistryCommand, TopicCommand,
 };
-use crate::config::KToolsConfig;
-use crate::schema_registry::SchemaRegistryClient;
+use crate::config::{KToolsConfig, KToolsContext};
+use crate::error::{ExitCode, ExitError};
+use crate::schema_registry::{ProtoSelector, SchemaRegistryClient};
 
 mod cli;
 mod config;
+mod error;
 mod kafka;
 mod schema_registry;
+mod terminal;
+
+pub use crate::error::exit_code;
 
 pub struct KTools {
     config: KToolsConfig,
@@ -42,7 +57,11 @@ impl KTools {
         let args = KToolsCliArgs::parse();
 
         match args.command {
-            Command::Kafka(command) => self.kafka(args.options, command).await,
+            // Copies name their source and destination contexts themselves
+            Command::Kafka(KafkaCommand::Copy(copy)) => self.copy(copy).await,
+            Command::Kafka(KafkaCommand::Context(command)) => {
+                self.kafka(args.options, command).await
+            }
             Command::SchemaRegistry(command) => self.schema_registry(args.options, command).await,
             Command::Config(command) => self.config(command).await,
         }
@@ -74,52 +93,470 @@ impl KTools {
         Ok(())
     }
 
-    async fn kafka(self, options: Options, command: KafkaCommand) -> anyhow::Result<()> {
+    async fn kafka(self, options: Options, command: KafkaContextCommand) -> anyhow::Result<()> {
         let context = options.context.as_deref().ok_or(anyhow!(
             "No context specified. Please, specify a context with the --context flag."
         ))?;
 
-        let context = self.config.contexts.get(context).with_context(|| {
-            anyhow!(
-                "Could not find the context {:?}, please, check your configuration file.",
-                context
-            )
-        })?;
+        let context = self.context(context)?;
 
-        let kafka_client = KafkaClient::configure(&self.config.user, context)?;
+        let mut kafka_client = KafkaClient::configure(&self.config.user, context)?;
 
         match command {
-            KafkaCommand::Consume { topic, decode } => {
-                kafka_client.consume(&topic, decode).await?;
+            KafkaContextCommand::Consume {
+                topic,
+                decode,
+                raw_format,
+                key_decode,
+                group,
+                commit,
+                from_beginning,
+                from_end,
+                offset,
+                from_timestamp,
+                partition,
+                max_messages,
+                until_end,
+                until_offset,
+                until_timestamp,
+            } => {
+                let start = match (from_beginning, from_end, offset, from_timestamp) {
+                    (true, _, _, _) => Some(StartPosition::Beginning),
+                    (_, true, _, _) => Some(StartPosition::End),
+                    (_, _, Some(offset), _) => Some(StartPosition::Offset(offset)),
+                    (_, _, _, Some(timestamp)) => Some(StartPosition::Timestamp(timestamp)),
+                    _ => None,
+                };
+
+                let options = ConsumeOptions {
+                    decoding: decode,
+                    raw_format,
+                    key_decoding: key_decode,
+                    group,
+                    commit,
+                    start,
+                    partitions: partition,
+                    max_messages,
+                    until_end,
+                    until_offset,
+                    until_timestamp,
+                };
+
+                kafka_client.consume(&topic, options).await?;
                 Ok(())
             }
-            KafkaCommand::Produce {
+            KafkaContextCommand::Get {
+                topic,
+                partition,
+                offset,
+                decode,
+                raw_format,
+                key_decode,
+            } => {
+                kafka_client
+                    .get(&topic, partition, offset, decode, raw_format, key_decode)
+                    .await
+            }
+            KafkaContextCommand::Produce {
                 topic,
                 message,
                 encode,
                 payload,
+                jsonl,
+                envelope,
+                max_in_flight,
+                delivery_timeout,
+                format,
                 key,
+                key_encode,
+                headers,
+                partition,
+                timestamp,
+                message_type,
+                schema_version,
             } => {
-                let payload: JsonValue = match (message, payload) {
-                    (Some(message), None) => {
-                        serde_json::from_str(&message).context("Invalid JSON message")?
+                let key = match key {
+                    Some(key) => Some(match key_encode {
+                        CodecKind::Raw => JsonValue::String(key),
+                        _ => serde_json::from_str(&key).context("Invalid JSON key")?,
+                    }),
+                    None => None,
+                };
+
+                let options = ProduceOptions {
+                    encoding: encode,
+                    key_encoding: key_encode,
+                    proto: ProtoSelector {
+                        version: schema_version,
+                        message_type,
+                    },
+                    format,
+                    envelope,
+                    key,
+                    headers,
+                    partition,
+                    timestamp,
+                };
+
+                if let Some(delivery_timeout) = delivery_timeout {
+                    kafka_client.set_delivery_timeout(Duration::from_millis(delivery_timeout))?;
+                }
+
+                if let Some(jsonl) = jsonl {
+                    let input: Box<dyn AsyncBufRead + Unpin> = if jsonl.as_os_str() == "-" {
+                        Box::new(BufReader::new(tokio::io::stdin()))
+                    } else {
+                        Box::new(BufReader::new(tokio::fs::File::open(jsonl).await?))
+                    };
+
+                    let summary = kafka_client
+                        .produce_batch(&topic, input, &options, max_in_flight)
+                        .await?;
+
+                    eprintln!(
+                        "Delivered: {}, Failed: {}",
+                        summary.delivered, summary.failed
+                    );
+
+                    if let Some(code) = summary.failure {
+                        return Err(ExitError::new(
+                            code,
+                            format!("{} messages could not be produced", summary.failed),
+                        )
+                        .into());
                     }
+
+                    return Ok(());
+                }
+
+                let record = match (message, payload) {
+                    (Some(message), None) => ProduceRecord::from_value(
+                        serde_json::from_str(&message).map_err(|err| {
+                            ExitError::new(
+                                ExitCode::InvalidMessage,
+                                format!("Invalid JSON message: {}", err),
+                            )
+                        })?,
+                    ),
                     (None, Some(payload)) => {
                         let text = fs::read_to_string(payload)?;
-                        serde_json::from_str(&text).context("Invalid JSON payload")?
+                        ProduceRecord::from_json(&text, envelope).map_err(|err| {
+                            ExitError::new(
+                                ExitCode::InvalidMessage,
+                                format!("Invalid JSON payload: {}", err),
+                            )
+                        })?
                     }
-                    _ => bail!("Either message or payload must be specified"),
+                    _ => bail!("Either message, payload or jsonl must be specified"),
                 };
 
-                kafka_client
-                    .produce(encode, &topic, key, serde_json::to_vec(&payload)?)
+                kafka_client.produce(&topic, record, &options).await?;
+
+                Ok(())
+            }
+            KafkaContextCommand::Topic(command) => Self::topic(kafka_client, command).await,
+            KafkaContextCommand::Config(command) => Self::kafka_config(kafka_client, command).await,
+            KafkaContextCommand::Group(command) => Self::group(kafka_client, command).await,
+            KafkaContextCommand::Cluster { format } => kafka_client.cluster_health(format),
+            KafkaContextCommand::Acl(command) => Self::acl(kafka_client, command),
+            KafkaContextCommand::Dlq(command) => Self::dlq(kafka_client, command).await,
+        }
+    }
+
+    async fn dlq(kafka_client: KafkaClient, command: DlqCommand) -> anyhow::Result<()> {
+        match command {
+            DlqCommand::Replay {
+                topic,
+                topic_header,
+                to_topic,
+                filters,
+                summary_headers,
+                replay_header,
+                max_replays,
+                partition,
+                max_messages,
+                max_in_flight,
+                dry_run,
+                yes,
+            } => {
+                let options = ReplayOptions {
+                    partitions: partition,
+                    max_messages,
+                    summary_headers,
+                    filters,
+                    topic_header,
+                    to_topic,
+                    replay_header,
+                    max_replays,
+                    max_in_flight,
+                };
+
+                let summary = kafka_client
+                    .replay_dlq(&topic, &options, dry_run, yes)
                     .await?;
 
+                if dry_run {
+                    eprintln!(
+                        "Dry run, {} messages would be replayed, {} skipped",
+                        summary.selected, summary.skipped
+                    );
+                    return Ok(());
+                }
+
+                eprintln!(
+                    "Replayed: {}, Failed: {}, Skipped: {}",
+                    summary.produced.delivered, summary.produced.failed, summary.skipped
+                );
+
+                if summary.produced.failed > 0 {
+                    return Err(ExitError::new(
+                        ExitCode::DeliveryFailed,
+                        format!("{} messages could not be replayed", summary.produced.failed),
+                    )
+                    .into());
+                }
+
                 Ok(())
             }
         }
     }
 
+    fn acl(kafka_client: KafkaClient, command: AclCommand) -> anyhow::Result<()> {
+        let acl_filter = |filter: AclFilterArgs| AclFilter {
+            resource_type: filter.resource_type,
+            resource: filter.resource,
+            pattern_type: filter.pattern_type,
+            principal: filter.principal,
+            host: filter.host,
+            operation: filter.operation,
+            permission: filter.permission,
+        };
+
+        match command {
+            AclCommand::List { filter, format } => {
+                kafka_client.list_acls(&acl_filter(filter), format)
+            }
+            AclCommand::Create {
+                resource_type,
+                resource,
+                pattern_type,
+                principal,
+                host,
+                operation,
+                permission,
+            } => kafka_client.create_acls(&NewAcl {
+                resource_type,
+                resource,
+                pattern_type,
+                principal,
+                host,
+                operations: operation,
+                permission,
+            }),
+            AclCommand::Delete { filter, all, yes } => {
+                let filter = acl_filter(filter);
+
+                if !all && !filter.is_narrowed() {
+                    bail!("Filter by resource, principal or operation, or pass --all");
+                }
+
+                let matching = kafka_client.describe_acls(&filter)?;
+
+                if matching.is_empty() {
+                    println!("No ACLs match the filters");
+                    return Ok(());
+                }
+
+                kafka::acl::print_acls(&matching, OutputFormat::Text)?;
+
+                let prompt = format!("Delete these {} ACLs?", matching.len());
+
+                if !yes && !terminal::confirm(&prompt)? {
+                    bail!("Aborted");
+                }
+
+                kafka_client.delete_acls(&filter)
+            }
+        }
+    }
+
+    async fn group(kafka_client: KafkaClient, command: GroupCommand) -> anyhow::Result<()> {
+        match command {
+            GroupCommand::List { format } => kafka_client.list_groups(format),
+            GroupCommand::Describe { group, format } => kafka_client.describe_group(&group, format),
+            GroupCommand::Lag {
+                group,
+                topic,
+                format,
+            } => kafka_client.group_lag(&group, topic.as_deref(), format),
+            GroupCommand::ResetOffsets {
+                group,
+                topic,
+                partition,
+                to_earliest,
+                to_latest,
+                to_offset,
+                shift_by,
+                to_timestamp,
+                from_file,
+                execute,
+                format,
+            } => {
+                let reset = match (to_earliest, to_latest, to_offset, shift_by, to_timestamp) {
+                    (true, _, _, _, _) => OffsetReset::Earliest,
+                    (_, true, _, _, _) => OffsetReset::Latest,
+                    (_, _, Some(offset), _, _) => OffsetReset::Offset(offset),
+                    (_, _, _, Some(shift), _) => OffsetReset::ShiftBy(shift),
+                    (_, _, _, _, Some(timestamp)) => OffsetReset::Timestamp(timestamp),
+                    _ => match from_file {
+                        Some(path) => OffsetReset::File(PartitionOffset::read_file(&path)?),
+                        None => bail!("A reset strategy must be specified"),
+                    },
+                };
+
+                kafka_client.reset_offsets(
+                    &group,
+                    topic.as_deref(),
+                    &partition,
+                    &reset,
+                    execute,
+                    format,
+                )
+            }
+            GroupCommand::Delete { group, yes } => {
+                let prompt = format!("Delete the groups {}?", group.join(", "));
+
+                if !yes && !terminal::confirm(&prompt)? {
+                    bail!("Aborted");
+                }
+
+                kafka_client.delete_groups(&group).await
+            }
+        }
+    }
+
+    fn context(&self, name: &str) -> anyhow::Result<&KToolsContext> {
+        self.config.contexts.get(name).with_context(|| {
+            anyhow!(
+                "Could not find the context {:?}, please, check your configuration file.",
+                name
+            )
+        })
+    }
+
+    async fn copy(self, args: CopyArgs) -> anyhow::Result<()> {
+        let source = KafkaClient::configure(&self.config.user, self.context(&args.from_context)?)?;
+        let destination =
+            KafkaClient::configure(&self.config.user, self.context(&args.to_context)?)?;
+
+        let start = match (args.offset, args.from_timestamp) {
+            (Some(offset), _) => StartPosition::Offset(offset),
+            (_, Some(timestamp)) => StartPosition::Timestamp(timestamp),
+            _ => StartPosition::Beginning,
+        };
+
+        let options = CopyOptions {
+            start,
+            partitions: args.partition,
+            until_offset: args.until_offset,
+            until_timestamp: args.until_timestamp,
+            max_messages: args.max_messages,
+            preserve_partition: args.preserve_partition,
+            decoding: args.decode,
+            encoding: args.encode.unwrap_or(args.decode),
+            key_decoding: args.key_decode,
+            key_encoding: args.key_encode.unwrap_or(args.key_decode),
+            proto: ProtoSelector {
+                version: args.schema_version,
+                message_type: args.message_type,
+            },
+            max_in_flight: args.max_in_flight,
+        };
+
+        let to_topic = args.to_topic.as_deref().unwrap_or(&args.from_topic);
+        let summary = source
+            .copy_to(&destination, &args.from_topic, to_topic, &options)
+            .await?;
+
+        eprintln!("Copied: {}, Failed: {}", summary.delivered, summary.failed);
+
+        if summary.failed > 0 {
+            return Err(ExitError::new(
+                ExitCode::DeliveryFailed,
+                format!("{} messages could not be copied", summary.failed),
+            )
+            .into());
+        }
+
+        Ok(())
+    }
+
+    async fn kafka_config(
+        kafka_client: KafkaClient,
+        command: KafkaConfigCommand,
+    ) -> anyhow::Result<()> {
+        let resource = |topic: Option<String>, broker: Option<i32>| match (topic, broker) {
+            (Some(topic), _) => Ok(ConfigResource::Topic(topic)),
+            (None, Some(broker)) => Ok(ConfigResource::Broker(broker)),
+            (None, None) => Err(anyhow!("Either topic or broker must be specified")),
+        };
+
+        match command {
+            KafkaConfigCommand::Describe {
+                topic,
+                broker,
+                format,
+            } => {
+                kafka_client
+                    .describe_configs(&resource(topic, broker)?, format)
+                    .await
+            }
+            KafkaConfigCommand::Alter {
+                topic,
+                broker,
+                set,
+                reset,
+                dry_run,
+                yes,
+            } => {
+                kafka_client
+                    .alter_configs(&resource(topic, broker)?, &set, &reset, dry_run, yes)
+                    .await
+            }
+        }
+    }
+
+    async fn topic(kafka_client: KafkaClient, command: TopicCommand) -> anyhow::Result<()> {
+        match command {
+            TopicCommand::List { filter, format } => {
+                kafka_client.list_topics(filter.as_deref(), format)
+            }
+            TopicCommand::Describe { topic, format } => kafka_client.describe_topic(&topic, format),
+            TopicCommand::Offsets { topic, at, format } => {
+                kafka_client.topic_offsets(&topic, at, format)
+            }
+            TopicCommand::Create {
+                topic,
+                partitions,
+                replication_factor,
+                configs,
+            } => {
+                kafka_client
+                    .create_topic(&topic, partitions, replication_factor, &configs)
+                    .await
+            }
+            TopicCommand::Delete { topic, yes } => {
+                let prompt = format!("Delete the topics {}?", topic.join(", "));
+
+                if !yes && !terminal::confirm(&prompt)? {
+                    bail!("Aborted");
+                }
+
+                kafka_client.delete_topics(&topic).await
+            }
+        }
+    }
+
     async fn schema_registry(
         self,
         options: Options,
@@ -129,12 +566,7 @@ impl KTools {
             "No context specified. Please, specify a context with the --context flag."
         ))?;
 
-        let context_config = self.config.contexts.get(context).with_context(|| {
-            anyhow!(
-                "Could not find the context {:?}, please, check your configuration file.",
-                context
-            )
-        })?;
+        let context_config = self.context(context)?;
 
         let schema_registry_client = SchemaRegistryClient::configure(context_config)?;
 
@@ -142,9 +574,28 @@ impl KTools {
             SchemaRegistryCommand::Download {
                 subject,
                 version,
+                id,
                 output,
             } => {
-                let schema = schema_registry_client.get_schema(&subject, version).await?;
+                let schema = match (id, subject) {
+                    (Some(id), _) => {
+                        let (schema, versions) =
+                            schema_registry_client.get_schema_by_id(id).await?;
+
+                        // Printed apart from the schema, which may be written to stdout
+                        eprintln!("Schema {} is used by:", id);
+
+                        for (subject, version) in versions {
+                            eprintln!("  {} version {}", subject, version);
+                        }
+
+                        schema
+                    }
+                    (None, Some(subject)) => {
+                        schema_registry_client.get_schema(&subject, version).await?
+                    }
+                    (None, None) => bail!("Either subject or id must be specified"),
+                };
 
                 match output {
                     Some(output) => {
@@ -157,7 +608,18 @@ impl KTools {
 
                 Ok(())
             }
-            SchemaRegistryCommand::Register { subject, schema } => {
+            SchemaRegistryCommand::Register {
+                subject,
+                schema,
+                check_only,
+                allow_new_subject,
+            } => {
+                if check_only {
+                    return schema_registry_client
+                        .check_compatibility(&subject, &schema, false, allow_new_subject)
+                        .await;
+                }
+
                 let registered_version = schema_registry_client
                     .register_schema(&subject, &schema)
                     .await?;
@@ -166,6 +628,70 @@ impl KTools {
 
                 Ok(())
             }
+            SchemaRegistryCommand::List {
+                prefix,
+                regex,
+                deleted,
+                format,
+            } => {
+                let regex = regex
+                    .map(|regex| Regex::new(&regex))
+                    .transpose()
+                    .context("Invalid regular expression")?;
+
+                schema_registry_client
+                    .list_subjects(prefix.as_deref(), regex.as_ref(), deleted, format)
+                    .await
+            }
+            SchemaRegistryCommand::Versions { subject, format } => {
+                let versions = schema_registry_client
+                    .get_subject_versions(&subject)
+                    .await?;
+
+                match format {
+                    OutputFormat::Text => {
+                        for version in versions {
+                            println!("{}", version);
+                        }
+                    }
+                    OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&versions)?),
+                }
+
+                Ok(())
+            }
+            SchemaRegistryCommand::Delete {
+                subject,
+                version,
+                permanent,
+                dry_run,
+                yes,
+            } => {
+                schema_registry_client
+                    .delete_subject(&subject, version, permanent, dry_run, yes)
+                    .await
+            }
+            SchemaRegistryCommand::Check {
+                subject,
+                schema,
+                all_versions,
+                allow_new_subject,
+            } => {
+                schema_registry_client
+                    .check_compatibility(&subject, &schema, all_versions, allow_new_subject)
+                    .await
+            }
+            SchemaRegistryCommand::Compatibility(command) => match command {
+                CompatibilityCommand::Get { subject, format } => {
+                    schema_registry_client
+                        .get_compatibility(subject.as_deref(), format)
+                        .await
+                }
+                CompatibilityCommand::Set { subject, level, .. } => {
+                    schema_registry_client
+                        .set_compatibility(subject.as_deref(), level)
+                        .await
+                }
+            },
             SchemaRegistryCommand::Diff {
                 subject,
                 version,
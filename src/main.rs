@@ -4,7 +4,10 @@ use ktools::KTools;
 async fn main() -> anyhow::Result<()> {
     tracing_subscriber::fmt::init();
 
-    KTools::new()?.parse_args_and_run().await?;
+    if let Err(err) = KTools::new()?.parse_args_and_run().await {
+        eprintln!("Error: {:?}", err);
+        std::process::exit(ktools::exit_code(&err));
+    }
 
     Ok(())
 }
@@ -1,33 +1,151 @@
 
-use std::path::PathBuf;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
 
 use anyhow::{bail, Context};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use regex::Regex;
+use reqwest::{Method, RequestBuilder, Url};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
 
 use schema_registry_converter::async_impl::easy_avro::{EasyAvroDecoder, EasyAvroEncoder};
 use schema_registry_converter::async_impl::easy_json::{EasyJsonDecoder, EasyJsonEncoder};
 
-use schema_registry_converter::async_impl::easy_proto_raw::{
-    EasyProtoRawDecoder, EasyProtoRawEncoder,
-};
-use schema_registry_converter::async_impl::proto_raw::RawDecodeResult;
 use schema_registry_converter::async_impl::schema_registry as sr;
 use schema_registry_converter::async_impl::schema_registry::SrSettings;
 use schema_registry_converter::avro_common::DecodeResult;
 use schema_registry_converter::schema_registry_common::{
-    RegisteredReference, SchemaType, SubjectNameStrategy, SuppliedSchema,
+    RegisteredReference, RegisteredSchema, SchemaType, SubjectNameStrategy, SuppliedSchema,
 };
 use serde_json::Value as JsonValue;
 
-use crate::cli::CodecKind;
+use crate::cli::{CodecKind, CompatibilityLevel, OutputFormat, RawFormat};
 use crate::config::KToolsContext;
+use crate::error::{ExitCode, ExitError};
+use crate::schema_registry::proto::ProtoSchema;
+use crate::terminal;
 
 pub mod config;
+mod proto;
+
+/// Selects the registered schema and the message type used to encode protobuf
+/// messages. Defaults to the latest version and the first declared message.
+#[derive(Debug, Default, Clone)]
+pub struct ProtoSelector {
+    pub version: Option<u32>,
+    pub message_type: Option<String>,
+}
+
+/// A schema version as returned by the registry REST API.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SubjectVersion {
+    subject: String,
+    version: u32,
+    id: u32,
+    /// Missing for Avro schemas
+    schema_type: Option<String>,
+}
+
+/// The error body returned by the registry REST API.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RegistryError {
+    error_code: i64,
+    message: String,
+}
+
+impl RegistryError {
+    /// Whether the error is about a subject, version or schema that does not exist.
+    fn is_not_found(err: &anyhow::Error) -> bool {
+        err.downcast_ref::<RegistryError>()
+            .is_some_and(|err| err.error_code / 100 == 404)
+    }
+
+    /// Whether the error is about a subject that does not exist.
+    fn is_subject_not_found(err: &anyhow::Error) -> bool {
+        err.downcast_ref::<RegistryError>()
+            .is_some_and(|err| err.error_code == 40401)
+    }
+}
+
+impl std::fmt::Display for RegistryError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} (error code {})", self.message, self.error_code)
+    }
+}
+
+impl std::error::Error for RegistryError {}
+
+/// A compatibility configuration as returned by the registry REST API.
+#[derive(Debug, Deserialize)]
+struct CompatibilityConfig {
+    #[serde(rename = "compatibilityLevel", alias = "compatibility")]
+    level: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Compatibility {
+    /// Missing for the global compatibility
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub subject: Option<String>,
+    pub compatibility: String,
+    /// Whether the subject has no compatibility of its own and uses the global one
+    pub inherited: bool,
+}
+
+/// The result of a compatibility check as returned by the registry REST API.
+#[derive(Debug, Deserialize)]
+struct CompatibilityCheck {
+    is_compatible: bool,
+    /// Only returned when the check is verbose
+    #[serde(default)]
+    messages: Vec<String>,
+}
+
+/// A schema as returned by the registry REST API when fetched by id.
+#[derive(Debug, Deserialize)]
+struct SchemaById {
+    schema: String,
+}
+
+/// A subject version as returned by the registry REST API.
+#[derive(Debug, Deserialize)]
+struct SubjectVersionRef {
+    subject: String,
+    version: u32,
+}
+
+/// A version of a subject about to be deleted.
+#[derive(Debug)]
+struct VersionDeletion {
+    version: u32,
+    id: u32,
+    soft_deleted: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SubjectSummary {
+    pub subject: String,
+    pub latest_version: u32,
+    pub schema_type: String,
+    pub id: u32,
+    pub deleted: bool,
+}
+
 pub struct SchemaRegistryClient {
     inner: SrSettings,
+    /// Client for the REST endpoints not covered by `SrSettings`, sharing its
+    /// url and credentials
+    http: reqwest::Client,
+    url: Url,
+    basic_auth: Option<(String, Option<String>)>,
     avro_decoder: EasyAvroDecoder,
     avro_encoder: EasyAvroEncoder,
-    proto_decoder: EasyProtoRawDecoder,
-    proto_encoder: EasyProtoRawEncoder,
+    proto_schemas: Mutex<HashMap<u32, Arc<ProtoSchema>>>,
     json_decoder: EasyJsonDecoder,
     json_encoder: EasyJsonEncoder,
 }
@@ -50,19 +168,26 @@ impl SchemaRegistryClient {
 
         let inner = builder.build()?;
 
+        let url = Url::parse(&sr_context.url)
+            .with_context(|| format!("Invalid schema registry url {}", sr_context.url))?;
+        let basic_auth = sr_context
+            .basic_auth
+            .as_ref()
+            .map(|auth| (auth.username.clone(), auth.password.clone()));
+
         let avro_decoder = EasyAvroDecoder::new(inner.clone());
         let avro_encoder = EasyAvroEncoder::new(inner.clone());
-        let proto_decoder = EasyProtoRawDecoder::new(inner.clone());
-        let proto_encoder = EasyProtoRawEncoder::new(inner.clone());
         let json_decoder = EasyJsonDecoder::new(inner.clone());
         let json_encoder = EasyJsonEncoder::new(inner.clone());
 
         Ok(Self {
             inner,
+            http: reqwest::Client::new(),
+            url,
+            basic_auth,
             avro_decoder,
             avro_encoder,
-            proto_decoder,
-            proto_encoder,
+            proto_schemas: Mutex::new(HashMap::new()),
             json_decoder,
             json_encoder,
         })
@@ -71,22 +196,20 @@ impl SchemaRegistryClient {
     pub async fn decode(
         &self,
         codec: CodecKind,
+        raw_format: RawFormat,
         payload: Option<&[u8]>,
     ) -> anyhow::Result<Option<JsonValue>> {
         match codec {
             CodecKind::Proto => {
-                if payload.is_none() {
+                let Some(payload) = payload else {
                     return Ok(None);
-                }
+                };
 
-                if let Some(RawDecodeResult { bytes, .. }) =
-                    self.proto_decoder.decode(payload).await?
-                {
-                    let json = JsonValue::try_from(bytes)?;
-                    return Ok(Some(json));
-                }
+                let (id, indexes, bytes) = proto::split_wire_format(payload)?;
+                let schema = self.get_proto_schema(id).await?;
 
-                Ok(None)
+                let json = schema.decode(&indexes, bytes)?;
+                Ok(Some(json))
             }
             CodecKind::Avro => {
                 if payload.is_none() {
@@ -109,11 +232,21 @@ impl SchemaRegistryClient {
                 Ok(None)
             }
             CodecKind::Raw => {
-                if payload.is_none() {
+                let Some(payload) = payload else {
                     return Ok(None);
-                }
+                };
+
+                let json = match raw_format {
+                    RawFormat::Text => JsonValue::String(String::from_utf8_lossy(payload).into()),
+                    RawFormat::Json => serde_json::from_slice(payload).unwrap_or_else(|_| {
+                        JsonValue::String(String::from_utf8_lossy(payload).into())
+                    }),
+                    RawFormat::Hex => JsonValue::String(
+                        payload.iter().map(|byte| format!("{:02x}", byte)).collect(),
+                    ),
+                    RawFormat::Base64 => JsonValue::String(BASE64.encode(payload)),
+                };
 
-                let json = JsonValue::try_from(payload)?;
                 Ok(Some(json))
             }
         }
@@ -123,21 +256,29 @@ impl SchemaRegistryClient {
         &self,
         codec: CodecKind,
         topic: &str,
+        is_key: bool,
         payload: &[u8],
+        proto: &ProtoSelector,
     ) -> anyhow::Result<Vec<u8>> {
-        let strategy = SubjectNameStrategy::TopicNameStrategy(topic.to_string(), false);
+        let strategy = SubjectNameStrategy::TopicNameStrategy(topic.to_string(), is_key);
 
         let bytes = match codec {
             CodecKind::Proto => {
-                self.proto_encoder
-                    .encode_single_message(payload, strategy)
-                    .await?
-            }
-            CodecKind::Avro => {
                 let json: JsonValue = serde_json::from_slice(payload)?;
+                let subject = match is_key {
+                    true => format!("{}-key", topic),
+                    false => format!("{}-value", topic),
+                };
 
-                println!("sending {:?}", json);
+                let (id, schema) = self
+                    .get_subject_proto_schema(&subject, proto.version)
+                    .await?;
+                let (indexes, bytes) = schema.encode(proto.message_type.as_deref(), &json)?;
 
+                proto::write_wire_format(id, &indexes, &bytes)
+            }
+            CodecKind::Avro => {
+                let json: JsonValue = serde_json::from_slice(payload)?;
                 self.avro_encoder.encode_struct(&json, &strategy).await?
             }
             CodecKind::Json => {
@@ -151,7 +292,133 @@ impl SchemaRegistryClient {
         Ok(bytes)
     }
 
+    /// Fetch a protobuf schema by id, along with every schema it references.
+    ///
+    /// Parsed schemas are cached, since every consumed message triggers a lookup.
+    async fn get_proto_schema(&self, id: u32) -> anyhow::Result<Arc<ProtoSchema>> {
+        if let Some(schema) = self.proto_schemas.lock().unwrap().get(&id) {
+            return Ok(schema.clone());
+        }
+
+        let registered = sr::get_schema_by_id_and_type(id, &self.inner, SchemaType::Protobuf)
+            .await
+            .with_context(|| format!("Could not fetch the schema with id {}", id))?;
+
+        self.parse_proto_schema(registered).await
+    }
+
+    /// Fetch the protobuf schema registered under a subject, returning its id.
+    async fn get_subject_proto_schema(
+        &self,
+        subject: &str,
+        version: Option<u32>,
+    ) -> anyhow::Result<(u32, Arc<ProtoSchema>)> {
+        let version = self.resolve_version(subject, version).await?;
+
+        let registered = sr::get_referenced_schema(
+            &self.inner,
+            &RegisteredReference {
+                subject: subject.to_string(),
+                version,
+                name: String::new(),
+            },
+        )
+        .await?;
+
+        let id = registered.id;
+
+        if let Some(schema) = self.proto_schemas.lock().unwrap().get(&id) {
+            return Ok((id, schema.clone()));
+        }
+
+        Ok((id, self.parse_proto_schema(registered).await?))
+    }
+
+    async fn parse_proto_schema(
+        &self,
+        registered: RegisteredSchema,
+    ) -> anyhow::Result<Arc<ProtoSchema>> {
+        let id = registered.id;
+        let references = self.resolve_references(registered.references).await?;
+        let schema = Arc::new(ProtoSchema::parse(&registered.schema, &references)?);
+
+        self.proto_schemas
+            .lock()
+            .unwrap()
+            .insert(id, schema.clone());
+
+        Ok(schema)
+    }
+
+    /// Fetch the referenced schemas recursively, dependencies first.
+    async fn resolve_references(
+        &self,
+        references: Vec<RegisteredReference>,
+    ) -> anyhow::Result<Vec<String>> {
+        let mut resolved = Vec::new();
+        let mut visited = Vec::new();
+        let mut pending = references;
+
+        while let Some(reference) = pending.pop() {
+            let key = (reference.subject.clone(), reference.version);
+
+            if visited.contains(&key) {
+                continue;
+            }
+            visited.push(key);
+
+            let schema = sr::get_referenced_schema(&self.inner, &reference).await?;
+
+            pending.extend(schema.references);
+            resolved.push(schema.schema);
+        }
+
+        resolved.reverse();
+
+        Ok(resolved)
+    }
+
     pub async fn get_schema(&self, subject: &str, version: Option<u32>) -> anyhow::Result<String> {
+        let search_version = self.resolve_version(subject, version).await?;
+
+        let schema = sr::get_referenced_schema(
+            &self.inner,
+            &RegisteredReference {
+                subject: subject.to_string(),
+                version: search_version,
+                name: String::new(),
+            },
+        )
+        .await?;
+
+        Ok(schema.schema)
+    }
+
+    /// Fetch the schema with the given id, along with the subject and version pairs
+    /// it is registered as.
+    pub async fn get_schema_by_id(&self, id: u32) -> anyhow::Result<(String, Vec<(String, u32)>)> {
+        let id_segment = id.to_string();
+
+        let schema: SchemaById = self
+            .send(self.request(Method::GET, &["schemas", "ids", &id_segment]))
+            .await
+            .with_context(|| format!("Could not fetch the schema with id {}", id))?;
+
+        let versions: Vec<SubjectVersionRef> = self
+            .send(self.request(Method::GET, &["schemas", "ids", &id_segment, "versions"]))
+            .await
+            .with_context(|| format!("Could not fetch the versions using schema {}", id))?;
+
+        let versions = versions
+            .into_iter()
+            .map(|version| (version.subject, version.version))
+            .collect();
+
+        Ok((schema.schema, versions))
+    }
+
+    /// Check that the version exists for the subject, defaulting to the latest one.
+    async fn resolve_version(&self, subject: &str, version: Option<u32>) -> anyhow::Result<u32> {
         let versions = self.get_subject_versions(subject).await?;
 
         let search_version = match version {
@@ -174,17 +441,7 @@ impl SchemaRegistryClient {
             None => *versions.last().context("No versions found")?,
         };
 
-        let schema = sr::get_referenced_schema(
-            &self.inner,
-            &RegisteredReference {
-                subject: subject.to_string(),
-                version: search_version,
-                name: String::new(),
-            },
-        )
-        .await?;
-
-        Ok(schema.schema)
+        Ok(search_version)
     }
 
     pub async fn get_subject_versions(&self, subject: &str) -> anyhow::Result<Vec<u32>> {
@@ -194,18 +451,7 @@ impl SchemaRegistryClient {
     }
 
     pub async fn register_schema(&self, subject: &str, schema: &PathBuf) -> anyhow::Result<u32> {
-        let schema_type = match schema
-            .extension()
-            .context("No extension found")?
-            .to_str()
-            .context("Invalid extension")?
-        {
-            "avsc" => SchemaType::Avro,
-            "json" => SchemaType::Json,
-            "proto" => SchemaType::Protobuf,
-            other => bail!("Unsupported schema type: {}", other),
-        };
-
+        let schema_type = schema_type(schema)?;
         let schema = std::fs::read_to_string(schema)?;
 
         let supplied_schema = SuppliedSchema {
@@ -219,4 +465,462 @@ impl SchemaRegistryClient {
 
         Ok(result.id)
     }
+
+    /// List the subjects, with their latest version, filtered by name.
+    pub async fn list_subjects(
+        &self,
+        prefix: Option<&str>,
+        regex: Option<&Regex>,
+        include_deleted: bool,
+        format: OutputFormat,
+    ) -> anyhow::Result<()> {
+        // The latest schema of every subject is fetched at once, the prefix being
+        // filtered by the registry and the regex here
+        let latest_schemas = move |deleted: bool| {
+            let mut request = self
+                .request(Method::GET, &["schemas"])
+                .query(&[("latestOnly", true), ("deleted", deleted)]);
+
+            if let Some(prefix) = prefix {
+                request = request.query(&[("subjectPrefix", prefix)]);
+            }
+
+            self.send::<Vec<SubjectVersion>>(request)
+        };
+
+        let active = latest_schemas(false).await?;
+
+        let deleted = match include_deleted {
+            true => {
+                let active = active
+                    .iter()
+                    .map(|latest| latest.subject.as_str())
+                    .collect::<HashSet<&str>>();
+
+                latest_schemas(true)
+                    .await?
+                    .into_iter()
+                    .filter(|latest| !active.contains(latest.subject.as_str()))
+                    .collect()
+            }
+            false => Vec::new(),
+        };
+
+        let mut summaries = active
+            .into_iter()
+            .map(|latest| (latest, false))
+            .chain(deleted.into_iter().map(|latest| (latest, true)))
+            .filter(|(latest, _)| regex.is_none_or(|regex| regex.is_match(&latest.subject)))
+            .map(|(latest, deleted)| SubjectSummary {
+                subject: latest.subject,
+                latest_version: latest.version,
+                schema_type: latest.schema_type.unwrap_or_else(|| "AVRO".into()),
+                id: latest.id,
+                deleted,
+            })
+            .collect::<Vec<_>>();
+
+        summaries.sort_by(|a, b| a.subject.cmp(&b.subject));
+
+        match format {
+            OutputFormat::Text => {
+                let rows = summaries
+                    .iter()
+                    .map(|summary| {
+                        let subject = match summary.deleted {
+                            true => format!("{} (deleted)", summary.subject),
+                            false => summary.subject.clone(),
+                        };
+
+                        [
+                            subject,
+                            summary.latest_version.to_string(),
+                            summary.schema_type.clone(),
+                            summary.id.to_string(),
+                        ]
+                    })
+                    .collect::<Vec<_>>();
+
+                terminal::print_table(["SUBJECT", "LATEST", "TYPE", "ID"], &rows);
+            }
+            OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&summaries)?),
+        }
+
+        Ok(())
+    }
+
+    /// Build a request to the registry, `path` being the unescaped path segments.
+    fn request(&self, method: Method, path: &[&str]) -> RequestBuilder {
+        let mut url = self.url.clone();
+
+        if let Ok(mut segments) = url.path_segments_mut() {
+            segments.pop_if_empty().extend(path);
+        }
+
+        let mut request = self
+            .http
+            .request(method, url)
+            .header("Accept", "application/vnd.schemaregistry.v1+json");
+
+        if let Some((username, password)) = &self.basic_auth {
+            request = request.basic_auth(username, password.as_ref());
+        }
+
+        request
+    }
+
+    /// Send a request, turning error responses into the message given by the registry.
+    async fn send<T: DeserializeOwned>(&self, request: RequestBuilder) -> anyhow::Result<T> {
+        let response = request.send().await?;
+        let status = response.status();
+
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+
+            match serde_json::from_str::<RegistryError>(&body) {
+                Ok(err) => return Err(err.into()),
+                Err(_) => bail!("The schema registry responded with {}: {}", status, body),
+            }
+        }
+
+        Ok(response.json().await?)
+    }
+
+    /// Soft delete a version of the subject, or every version when none is given,
+    /// then permanently delete it when `permanent` is set. The versions and ids
+    /// removed are shown before asking for confirmation.
+    pub async fn delete_subject(
+        &self,
+        subject: &str,
+        version: Option<u32>,
+        permanent: bool,
+        dry_run: bool,
+        yes: bool,
+    ) -> anyhow::Result<()> {
+        let deletions = self.version_deletions(subject, version, permanent).await?;
+
+        if deletions.is_empty() {
+            println!("Nothing to delete");
+            return Ok(());
+        }
+
+        let kind = match permanent {
+            true => "permanently",
+            false => "soft",
+        };
+
+        println!("Versions of {} to be {} deleted:", subject, kind);
+
+        let rows = deletions
+            .iter()
+            .map(|deletion| {
+                [
+                    deletion.version.to_string(),
+                    deletion.id.to_string(),
+                    match deletion.soft_deleted {
+                        true => "soft-deleted".into(),
+                        false => "active".into(),
+                    },
+                ]
+            })
+            .collect::<Vec<_>>();
+
+        terminal::print_table(["VERSION", "ID", "STATE"], &rows);
+
+        if dry_run || (!yes && !terminal::confirm("Delete these versions?")?) {
+            return Ok(());
+        }
+
+        let mut path = vec!["subjects", subject];
+        let version = version.map(|version| version.to_string());
+
+        if let Some(version) = &version {
+            path.extend(["versions", version.as_str()]);
+        }
+
+        // Permanent deletes are only accepted once the versions are soft deleted
+        if deletions.iter().any(|deletion| !deletion.soft_deleted) {
+            self.send::<JsonValue>(self.request(Method::DELETE, &path))
+                .await
+                .context("Soft delete failed")?;
+        }
+
+        if permanent {
+            self.send::<JsonValue>(
+                self.request(Method::DELETE, &path)
+                    .query(&[("permanent", "true")]),
+            )
+            .await
+            .context("Permanent delete failed")?;
+        }
+
+        let versions = deletions
+            .iter()
+            .map(|deletion| deletion.version.to_string())
+            .collect::<Vec<String>>()
+            .join(", ");
+
+        let deleted = match permanent {
+            true => "Permanently deleted",
+            false => "Soft deleted",
+        };
+
+        println!("{} versions {} of {}", deleted, versions, subject);
+
+        Ok(())
+    }
+
+    /// The versions removed by a delete, soft-deleted versions being only
+    /// removed by permanent deletes.
+    async fn version_deletions(
+        &self,
+        subject: &str,
+        version: Option<u32>,
+        permanent: bool,
+    ) -> anyhow::Result<Vec<VersionDeletion>> {
+        let active = match self.subject_versions(subject, false).await {
+            Ok(versions) => versions,
+            Err(err) if RegistryError::is_not_found(&err) => Vec::new(),
+            Err(err) => return Err(err),
+        };
+
+        let versions = match permanent {
+            true => self.subject_versions(subject, true).await?,
+            false => active.clone(),
+        };
+
+        let mut deletions = Vec::new();
+
+        for candidate in versions {
+            if version.is_some_and(|version| version != candidate) {
+                continue;
+            }
+
+            let registered: SubjectVersion = self
+                .send(
+                    self.request(
+                        Method::GET,
+                        &["subjects", subject, "versions", &candidate.to_string()],
+                    )
+                    .query(&[("deleted", "true")]),
+                )
+                .await?;
+
+            deletions.push(VersionDeletion {
+                version: candidate,
+                id: registered.id,
+                soft_deleted: !active.contains(&candidate),
+            });
+        }
+
+        if let Some(version) = version {
+            if deletions.is_empty() {
+                bail!("Subject {} does not have version {}", subject, version);
+            }
+        }
+
+        Ok(deletions)
+    }
+
+    /// The versions of a subject, including the soft-deleted ones when asked.
+    async fn subject_versions(&self, subject: &str, deleted: bool) -> anyhow::Result<Vec<u32>> {
+        self.send(
+            self.request(Method::GET, &["subjects", subject, "versions"])
+                .query(&[("deleted", deleted.to_string())]),
+        )
+        .await
+    }
+
+    /// Print the compatibility of the subject, or the global one when no subject is given.
+    pub async fn get_compatibility(
+        &self,
+        subject: Option<&str>,
+        format: OutputFormat,
+    ) -> anyhow::Result<()> {
+        let compatibility = self.compatibility(subject).await?;
+
+        match format {
+            OutputFormat::Text => match (&compatibility.subject, compatibility.inherited) {
+                (Some(_), true) => {
+                    println!("{} (inherited from global)", compatibility.compatibility)
+                }
+                _ => println!("{}", compatibility.compatibility),
+            },
+            OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&compatibility)?),
+        }
+
+        Ok(())
+    }
+
+    /// Set the compatibility of the subject, or the global one when no subject is
+    /// given. Without a level the subject goes back to inheriting the global one.
+    pub async fn set_compatibility(
+        &self,
+        subject: Option<&str>,
+        level: Option<CompatibilityLevel>,
+    ) -> anyhow::Result<()> {
+        let path = match subject {
+            Some(subject) => vec!["config", subject],
+            None => vec!["config"],
+        };
+
+        match level {
+            Some(level) => {
+                let body = serde_json::json!({ "compatibility": level.as_registry_str() });
+
+                self.send::<JsonValue>(self.request(Method::PUT, &path).json(&body))
+                    .await?;
+            }
+            None => {
+                self.send::<JsonValue>(self.request(Method::DELETE, &path))
+                    .await?;
+            }
+        }
+
+        let compatibility = self.compatibility(subject).await?;
+
+        match subject {
+            Some(subject) if compatibility.inherited => println!(
+                "Compatibility of {}: {} (inherited from global)",
+                subject, compatibility.compatibility
+            ),
+            Some(subject) => println!(
+                "Compatibility of {}: {}",
+                subject, compatibility.compatibility
+            ),
+            None => println!("Global compatibility: {}", compatibility.compatibility),
+        }
+
+        Ok(())
+    }
+
+    /// The compatibility of the subject, falling back to the global one when the
+    /// subject has none of its own.
+    async fn compatibility(&self, subject: Option<&str>) -> anyhow::Result<Compatibility> {
+        if let Some(subject) = subject {
+            match self
+                .send::<CompatibilityConfig>(self.request(Method::GET, &["config", subject]))
+                .await
+            {
+                Ok(config) => {
+                    return Ok(Compatibility {
+                        subject: Some(subject.to_string()),
+                        compatibility: config.level,
+                        inherited: false,
+                    })
+                }
+                Err(err) if RegistryError::is_not_found(&err) => {}
+                Err(err) => return Err(err),
+            }
+        }
+
+        let global: CompatibilityConfig = self.send(self.request(Method::GET, &["config"])).await?;
+
+        Ok(Compatibility {
+            subject: subject.map(str::to_string),
+            compatibility: global.level,
+            inherited: subject.is_some(),
+        })
+    }
+
+    /// Check whether the schema file can be registered under the subject, failing
+    /// with the reasons given by the registry when it cannot.
+    ///
+    /// The registry applies the compatibility level of the subject: checking
+    /// `all_versions` only tests every version under a transitive level, and
+    /// otherwise the same versions as a check against the latest one. A subject
+    /// that does not exist yet fails the check unless `allow_new_subject` is set.
+    pub async fn check_compatibility(
+        &self,
+        subject: &str,
+        schema: &PathBuf,
+        all_versions: bool,
+        allow_new_subject: bool,
+    ) -> anyhow::Result<()> {
+        let schema_type = match schema_type(schema)? {
+            SchemaType::Avro => "AVRO",
+            SchemaType::Json => "JSON",
+            SchemaType::Protobuf => "PROTOBUF",
+            _ => bail!("Unsupported schema type"),
+        };
+
+        let body = serde_json::json!({
+            "schema": std::fs::read_to_string(schema)?,
+            "schemaType": schema_type,
+        });
+
+        let path = match all_versions {
+            true => vec!["compatibility", "subjects", subject, "versions"],
+            false => vec!["compatibility", "subjects", subject, "versions", "latest"],
+        };
+
+        let request = self
+            .request(Method::POST, &path)
+            .query(&[("verbose", "true")])
+            .json(&body);
+
+        let check: CompatibilityCheck = match self.send(request).await {
+            Ok(check) => check,
+            Err(err) if allow_new_subject && RegistryError::is_subject_not_found(&err) => {
+                eprintln!("Subject {} has no versions to check against", subject);
+                return Ok(());
+            }
+            Err(err) if RegistryError::is_subject_not_found(&err) => {
+                return Err(err).with_context(|| {
+                    format!(
+                        "Subject {} has no versions to check against, pass --allow-new-subject \
+                         to accept the schema",
+                        subject
+                    )
+                });
+            }
+            Err(err) => return Err(err),
+        };
+
+        if check.is_compatible {
+            println!("The schema is compatible with {}", subject);
+            return Ok(());
+        }
+
+        for message in &check.messages {
+            eprintln!("{}", message);
+        }
+
+        Err(ExitError::new(
+            ExitCode::IncompatibleSchema,
+            format!("The schema is not compatible with {}", subject),
+        )
+        .into())
+    }
+}
+
+impl CompatibilityLevel {
+    fn as_registry_str(self) -> &'static str {
+        match self {
+            CompatibilityLevel::Backward => "BACKWARD",
+            CompatibilityLevel::BackwardTransitive => "BACKWARD_TRANSITIVE",
+            CompatibilityLevel::Forward => "FORWARD",
+            CompatibilityLevel::ForwardTransitive => "FORWARD_TRANSITIVE",
+            CompatibilityLevel::Full => "FULL",
+            CompatibilityLevel::FullTransitive => "FULL_TRANSITIVE",
+            CompatibilityLevel::None => "NONE",
+        }
+    }
+}
+
+/// The type of a schema file, told by its extension.
+fn schema_type(schema: &Path) -> anyhow::Result<SchemaType> {
+    let schema_type = match schema
+        .extension()
+        .context("No extension found")?
+        .to_str()
+        .context("Invalid extension")?
+    {
+        "avsc" => SchemaType::Avro,
+        "json" => SchemaType::Json,
+        "proto" => SchemaType::Protobuf,
+        other => bail!("Unsupported schema type: {}", other),
+    };
+
+    Ok(schema_type)
 }
@@ -0,0 +1,895 @@
+use anyhow::{anyhow, bail, Context as _};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use bytes::Bytes;
+use protofish::context::{Context, MessageField, MessageInfo, Multiplicity, ValueType};
+use protofish::decode::{EnumValue, FieldValue, MessageValue, PackedArray, Value};
+use serde_json::{Map, Number, Value as JsonValue};
+
+/// Magic byte that prefixes every message serialized with the Confluent wire format.
+const MAGIC_BYTE: u8 = 0;
+
+/// A protobuf schema fetched from the schema registry, parsed together with all
+/// of its references.
+pub struct ProtoSchema {
+    context: Context,
+    messages: Vec<DeclaredMessage>,
+    enums: Vec<DeclaredEnum>,
+}
+
+/// A message declared in the root `.proto` file, identified both by its fully
+/// qualified name and by the index path used in the wire format header.
+struct DeclaredMessage {
+    full_name: String,
+    indexes: Vec<i32>,
+}
+
+/// An enum declared in the schema or one of its references, along with its
+/// values, used to resolve enum values given by name in JSON.
+struct DeclaredEnum {
+    full_name: String,
+    values: Vec<(String, i64)>,
+}
+
+impl ProtoSchema {
+    /// Parse a schema and its (already resolved) references.
+    pub fn parse(schema: &str, references: &[String]) -> anyhow::Result<Self> {
+        let files = references
+            .iter()
+            .map(String::as_str)
+            .chain(std::iter::once(schema))
+            .collect::<Vec<&str>>();
+
+        let context = Context::parse(&files)
+            .map_err(|err| anyhow::anyhow!("Could not parse the protobuf schema: {}", err))?;
+
+        Ok(Self {
+            context,
+            messages: declared_messages(schema),
+            enums: files.into_iter().flat_map(declared_enums).collect(),
+        })
+    }
+
+    /// Find the message referenced by the message indexes of the wire format header.
+    pub fn message_by_indexes(&self, indexes: &[i32]) -> anyhow::Result<&MessageInfo> {
+        let declared = self
+            .messages
+            .iter()
+            .find(|message| message.indexes == indexes)
+            .with_context(|| format!("No message found for message indexes {:?}", indexes))?;
+
+        self.context
+            .get_message(&declared.full_name)
+            .with_context(|| format!("Message {} not found in the schema", declared.full_name))
+    }
+
+    /// Decode a protobuf payload (without the wire format header) into JSON.
+    pub fn decode(&self, indexes: &[i32], bytes: &[u8]) -> anyhow::Result<JsonValue> {
+        let message = self.message_by_indexes(indexes)?;
+        let value = message.decode(bytes, &self.context);
+
+        Ok(message_to_json(&value, &self.context))
+    }
+
+    /// Find the value of an enum value by name.
+    fn enum_value(&self, enum_name: &str, name: &str) -> Option<i64> {
+        self.enums
+            .iter()
+            .find(|declared| declared.full_name == enum_name)?
+            .values
+            .iter()
+            .find(|(value_name, _)| value_name == name)
+            .map(|(_, value)| *value)
+    }
+
+    /// Encode a JSON document as the given message type, or as the first message
+    /// declared in the schema when no type is given.
+    ///
+    /// Returns the message indexes of the encoded message along with its bytes.
+    pub fn encode(
+        &self,
+        message_type: Option<&str>,
+        json: &JsonValue,
+    ) -> anyhow::Result<(Vec<i32>, Vec<u8>)> {
+        let declared = match message_type {
+            Some(message_type) => self
+                .messages
+                .iter()
+                .find(|message| {
+                    message.full_name == message_type
+                        || message.full_name.ends_with(&format!(".{}", message_type))
+                })
+                .with_context(|| {
+                    format!(
+                        "Message type {} not found. Available types: {}.",
+                        message_type,
+                        self.message_types().join(", ")
+                    )
+                })?,
+            None => self
+                .messages
+                .first()
+                .context("The schema does not declare any message")?,
+        };
+
+        let info = self
+            .context
+            .get_message(&declared.full_name)
+            .with_context(|| format!("Message {} not found in the schema", declared.full_name))?;
+
+        let message = json_to_message(json, info, self)?;
+        let bytes = message.encode(&self.context).to_vec();
+
+        Ok((declared.indexes.clone(), bytes))
+    }
+
+    /// The fully qualified names of the messages declared in the schema.
+    pub fn message_types(&self) -> Vec<String> {
+        self.messages
+            .iter()
+            .map(|message| message.full_name.clone())
+            .collect()
+    }
+}
+
+/// Split a payload serialized with the Confluent wire format into the schema id,
+/// the message indexes and the protobuf encoded message.
+pub fn split_wire_format(payload: &[u8]) -> anyhow::Result<(u32, Vec<i32>, &[u8])> {
+    if payload.len() < 5 || payload[0] != MAGIC_BYTE {
+        bail!("Payload is not serialized with the schema registry wire format");
+    }
+
+    let id = u32::from_be_bytes([payload[1], payload[2], payload[3], payload[4]]);
+    let mut rest = &payload[5..];
+
+    let count = read_zigzag_varint(&mut rest)?;
+
+    // A single zero stands for the first message of the schema
+    if count == 0 {
+        return Ok((id, vec![0], rest));
+    }
+
+    // Every index takes at least one byte, which bounds the count before allocating
+    if count < 0 || count as u64 > rest.len() as u64 {
+        bail!("Invalid message index count {count} in the wire format header");
+    }
+
+    let mut indexes = Vec::with_capacity(count as usize);
+
+    for _ in 0..count {
+        indexes.push(read_zigzag_varint(&mut rest)? as i32);
+    }
+
+    Ok((id, indexes, rest))
+}
+
+/// Prefix a protobuf encoded message with the schema registry wire format header.
+pub fn write_wire_format(id: u32, indexes: &[i32], bytes: &[u8]) -> Vec<u8> {
+    let mut payload = Vec::with_capacity(bytes.len() + 6);
+    payload.push(MAGIC_BYTE);
+    payload.extend_from_slice(&id.to_be_bytes());
+
+    // The first message is encoded as a single zero instead of `[1, 0]`
+    if indexes == [0] {
+        payload.push(0);
+    } else {
+        write_zigzag_varint(&mut payload, indexes.len() as i64);
+
+        for index in indexes {
+            write_zigzag_varint(&mut payload, i64::from(*index));
+        }
+    }
+
+    payload.extend_from_slice(bytes);
+    payload
+}
+
+fn write_zigzag_varint(buffer: &mut Vec<u8>, value: i64) {
+    let mut value = ((value << 1) ^ (value >> 63)) as u64;
+
+    while value >= 0x80 {
+        buffer.push((value as u8 & 0x7f) | 0x80);
+        value >>= 7;
+    }
+
+    buffer.push(value as u8);
+}
+
+fn read_zigzag_varint(bytes: &mut &[u8]) -> anyhow::Result<i64> {
+    let mut value: u64 = 0;
+
+    for shift in (0..64).step_by(7) {
+        let (&byte, rest) = bytes
+            .split_first()
+            .context("Unexpected end of the message indexes")?;
+        *bytes = rest;
+
+        value |= u64::from(byte & 0x7f) << shift;
+
+        if byte & 0x80 == 0 {
+            return Ok(((value >> 1) as i64) ^ -((value & 1) as i64));
+        }
+    }
+
+    bail!("Invalid varint in the message indexes")
+}
+
+/// List the messages declared in a `.proto` file in declaration order, keeping
+/// track of the nesting so each one can be mapped to its message indexes.
+fn declared_messages(schema: &str) -> Vec<DeclaredMessage> {
+    enum Scope {
+        Message { name: String, children: i32 },
+        Other,
+    }
+
+    let tokens = tokenize(schema);
+
+    let mut package = None;
+    let mut top_level = 0;
+    let mut scopes: Vec<Scope> = Vec::new();
+    let mut indexes: Vec<i32> = Vec::new();
+    let mut messages = Vec::new();
+
+    let mut i = 0;
+    while i < tokens.len() {
+        match tokens[i] {
+            "package" if scopes.is_empty() => {
+                package = tokens.get(i + 1).map(|name| name.to_string());
+                i += 1;
+            }
+            "message" if tokens.get(i + 2) == Some(&"{") => {
+                let name = tokens[i + 1].to_string();
+
+                let index = match scopes.last_mut() {
+                    Some(Scope::Message { children, .. }) => {
+                        *children += 1;
+                        *children - 1
+                    }
+                    _ => {
+                        top_level += 1;
+                        top_level - 1
+                    }
+                };
+                indexes.push(index);
+
+                let mut path = package.iter().cloned().collect::<Vec<String>>();
+                path.extend(scopes.iter().filter_map(|scope| match scope {
+                    Scope::Message { name, .. } => Some(name.clone()),
+                    Scope::Other => None,
+                }));
+                path.push(name.clone());
+
+                messages.push(DeclaredMessage {
+                    full_name: path.join("."),
+                    indexes: indexes.clone(),
+                });
+
+                scopes.push(Scope::Message { name, children: 0 });
+                i += 2;
+            }
+            "{" => scopes.push(Scope::Other),
+            "}" => {
+                if let Some(Scope::Message { .. }) = scopes.pop() {
+                    indexes.pop();
+                }
+            }
+            _ => {}
+        }
+
+        i += 1;
+    }
+
+    messages
+}
+
+/// List the enums declared in a `.proto` file with their values. The parsed
+/// context does not expose enum values by name, so they are read from the file.
+fn declared_enums(schema: &str) -> Vec<DeclaredEnum> {
+    let tokens = tokenize(schema);
+
+    let mut package = None;
+    let mut scopes: Vec<Option<String>> = Vec::new();
+    let mut enums = Vec::new();
+
+    let mut i = 0;
+    while i < tokens.len() {
+        match tokens[i] {
+            "package" if scopes.is_empty() => {
+                package = tokens.get(i + 1).map(|name| name.to_string());
+                i += 1;
+            }
+            "message" if tokens.get(i + 2) == Some(&"{") => {
+                scopes.push(Some(tokens[i + 1].to_string()));
+                i += 2;
+            }
+            "enum" if tokens.get(i + 2) == Some(&"{") => {
+                let mut path = package.iter().cloned().collect::<Vec<String>>();
+                path.extend(scopes.iter().flatten().cloned());
+                path.push(tokens[i + 1].to_string());
+
+                let (values, end) = enum_values(&tokens, i + 3);
+                enums.push(DeclaredEnum {
+                    full_name: path.join("."),
+                    values,
+                });
+
+                // Continue at the closing brace of the enum
+                i = end;
+            }
+            "{" => scopes.push(None),
+            "}" => {
+                scopes.pop();
+            }
+            _ => {}
+        }
+
+        i += 1;
+    }
+
+    enums
+}
+
+/// Read the values of an enum body starting at `start`, skipping options and
+/// reserved ranges. Returns the values and the position of the closing brace.
+fn enum_values(tokens: &[&str], start: usize) -> (Vec<(String, i64)>, usize) {
+    let mut values = Vec::new();
+    let mut i = start;
+
+    while i < tokens.len() && tokens[i] != "}" {
+        let statement_start = i;
+        let mut depth = 0;
+
+        while i < tokens.len() {
+            match tokens[i] {
+                "[" | "{" => depth += 1,
+                "]" | "}" if depth > 0 => depth -= 1,
+                "}" => break,
+                ";" if depth == 0 => break,
+                _ => {}
+            }
+            i += 1;
+        }
+
+        if let [name, "=", value @ ..] = &tokens[statement_start..i] {
+            let value = match value {
+                ["-", literal, ..] => parse_int_literal(literal).map(|value| -value),
+                [literal, ..] => parse_int_literal(literal),
+                [] => None,
+            };
+
+            if let Some(value) = value {
+                values.push((name.to_string(), value));
+            }
+        }
+
+        if tokens.get(i) == Some(&";") {
+            i += 1;
+        }
+    }
+
+    (values, i)
+}
+
+/// Parse a decimal, hexadecimal or octal integer literal.
+fn parse_int_literal(literal: &str) -> Option<i64> {
+    if let Some(hex) = literal
+        .strip_prefix("0x")
+        .or_else(|| literal.strip_prefix("0X"))
+    {
+        i64::from_str_radix(hex, 16).ok()
+    } else if literal.len() > 1 && literal.starts_with('0') {
+        i64::from_str_radix(&literal[1..], 8).ok()
+    } else {
+        literal.parse().ok()
+    }
+}
+
+/// Split a `.proto` file in identifiers and punctuation, dropping comments and
+/// string literals.
+fn tokenize(schema: &str) -> Vec<&str> {
+    let bytes = schema.as_bytes();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let c = bytes[i];
+
+        if c.is_ascii_whitespace() {
+            i += 1;
+        } else if schema[i..].starts_with("//") {
+            i = schema[i..].find('\n').map_or(bytes.len(), |end| i + end);
+        } else if schema[i..].starts_with("/*") {
+            i = schema[i + 2..]
+                .find("*/")
+                .map_or(bytes.len(), |end| i + end + 4);
+        } else if c == b'"' || c == b'\'' {
+            i += 1;
+            while i < bytes.len() && bytes[i] != c {
+                if bytes[i] == b'\\' {
+                    i += 1;
+                }
+                i += 1;
+            }
+            i += 1;
+        } else if c.is_ascii_alphanumeric() || c == b'_' || c == b'.' {
+            let start = i;
+            while i < bytes.len()
+                && (bytes[i].is_ascii_alphanumeric() || bytes[i] == b'_' || bytes[i] == b'.')
+            {
+                i += 1;
+            }
+            tokens.push(&schema[start..i]);
+        } else {
+            let len = schema[i..].chars().next().map_or(1, char::len_utf8);
+            tokens.push(&schema[i..i + len]);
+            i += len;
+        }
+    }
+
+    tokens
+}
+
+/// Convert a decoded message to JSON following the proto3 JSON mapping.
+pub fn message_to_json(message: &MessageValue, context: &Context) -> JsonValue {
+    let info = context.resolve_message(message.msg_ref);
+    let mut object = Map::new();
+
+    for FieldValue { number, value } in &message.fields {
+        // Fields unknown to the schema are not part of the JSON representation
+        let Some(field) = info.get_field(*number) else {
+            continue;
+        };
+
+        let name = json_name(&field.name);
+
+        match field.multiplicity {
+            Multiplicity::Repeated | Multiplicity::RepeatedPacked => {
+                if let Some(entry) = map_entry(&field.field_type, value, context) {
+                    let map = object
+                        .entry(name)
+                        .or_insert_with(|| JsonValue::Object(Map::new()));
+
+                    if let (JsonValue::Object(map), Some((key, value))) = (map, entry) {
+                        map.insert(key, value);
+                    }
+
+                    continue;
+                }
+
+                let array = object
+                    .entry(name)
+                    .or_insert_with(|| JsonValue::Array(Vec::new()));
+
+                if let JsonValue::Array(array) = array {
+                    match value {
+                        Value::Packed(packed) => {
+                            array.extend(packed_to_json(packed, &field.field_type, context))
+                        }
+                        value => array.push(value_to_json(value, context)),
+                    }
+                }
+            }
+            Multiplicity::Single | Multiplicity::Optional => {
+                object.insert(name, value_to_json(value, context));
+            }
+        }
+    }
+
+    JsonValue::Object(object)
+}
+
+/// Returns `Some` when the field is a map entry, with the entry itself when it
+/// could be converted.
+fn map_entry(
+    field_type: &ValueType,
+    value: &Value,
+    context: &Context,
+) -> Option<Option<(String, JsonValue)>> {
+    let ValueType::Message(msg_ref) = field_type else {
+        return None;
+    };
+
+    let info = context.resolve_message(*msg_ref);
+    let is_map_entry = info.name.ends_with("Entry")
+        && info.iter_fields().count() == 2
+        && info.get_field(1).is_some_and(|field| field.name == "key")
+        && info.get_field(2).is_some_and(|field| field.name == "value");
+
+    if !is_map_entry {
+        return None;
+    }
+
+    let Value::Message(entry) = value else {
+        return Some(None);
+    };
+
+    let mut key = String::new();
+    let mut entry_value = JsonValue::Null;
+
+    for field in &entry.fields {
+        match field.number {
+            1 => {
+                key = match value_to_json(&field.value, context) {
+                    JsonValue::String(key) => key,
+                    other => other.to_string(),
+                }
+            }
+            2 => entry_value = value_to_json(&field.value, context),
+            _ => {}
+        }
+    }
+
+    Some(Some((key, entry_value)))
+}
+
+fn value_to_json(value: &Value, context: &Context) -> JsonValue {
+    match value {
+        Value::Double(v) => float_to_json(*v),
+        Value::Float(v) => float_to_json(f64::from(*v)),
+        Value::Int32(v) | Value::SInt32(v) | Value::SFixed32(v) => JsonValue::from(*v),
+        Value::UInt32(v) | Value::Fixed32(v) => JsonValue::from(*v),
+        // 64 bit integers are represented as strings to avoid precision loss
+        Value::Int64(v) | Value::SInt64(v) | Value::SFixed64(v) => JsonValue::String(v.to_string()),
+        Value::UInt64(v) | Value::Fixed64(v) => JsonValue::String(v.to_string()),
+        Value::Bool(v) => JsonValue::Bool(*v),
+        Value::String(v) => JsonValue::String(v.clone()),
+        Value::Bytes(v) => JsonValue::String(BASE64.encode(v)),
+        Value::Message(message) => message_to_json(message, context),
+        Value::Enum(enum_value) => context
+            .resolve_enum(enum_value.enum_ref)
+            .get_field_by_value(enum_value.value)
+            .map(|field| JsonValue::String(field.name.clone()))
+            .unwrap_or_else(|| JsonValue::from(enum_value.value)),
+        Value::Packed(packed) => {
+            JsonValue::Array(packed_to_json(packed, &ValueType::Int32, context))
+        }
+        Value::Incomplete(..) | Value::Unknown(..) => JsonValue::Null,
+    }
+}
+
+fn packed_to_json(
+    packed: &PackedArray,
+    field_type: &ValueType,
+    context: &Context,
+) -> Vec<JsonValue> {
+    fn map<T>(values: &[T], f: impl Fn(&T) -> JsonValue) -> Vec<JsonValue> {
+        values.iter().map(f).collect()
+    }
+
+    match packed {
+        PackedArray::Double(v) => map(v, |v| float_to_json(*v)),
+        PackedArray::Float(v) => map(v, |v| float_to_json(f64::from(*v))),
+        PackedArray::Int32(v) => match field_type {
+            // Packed enums are decoded as plain integers
+            ValueType::Enum(enum_ref) => {
+                let info = context.resolve_enum(*enum_ref);
+                map(v, |v| {
+                    info.get_field_by_value(i64::from(*v))
+                        .map(|field| JsonValue::String(field.name.clone()))
+                        .unwrap_or_else(|| JsonValue::from(*v))
+                })
+            }
+            _ => map(v, |v| JsonValue::from(*v)),
+        },
+        PackedArray::SInt32(v) | PackedArray::SFixed32(v) => map(v, |v| JsonValue::from(*v)),
+        PackedArray::UInt32(v) | PackedArray::Fixed32(v) => map(v, |v| JsonValue::from(*v)),
+        PackedArray::Int64(v) | PackedArray::SInt64(v) | PackedArray::SFixed64(v) => {
+            map(v, |v| JsonValue::String(v.to_string()))
+        }
+        PackedArray::UInt64(v) | PackedArray::Fixed64(v) => {
+            map(v, |v| JsonValue::String(v.to_string()))
+        }
+        PackedArray::Bool(v) => map(v, |v| JsonValue::Bool(*v)),
+    }
+}
+
+fn float_to_json(value: f64) -> JsonValue {
+    match Number::from_f64(value) {
+        Some(number) => JsonValue::Number(number),
+        None if value.is_nan() => JsonValue::String("NaN".into()),
+        None if value.is_sign_positive() => JsonValue::String("Infinity".into()),
+        None => JsonValue::String("-Infinity".into()),
+    }
+}
+
+/// Build a message from JSON following the proto3 JSON mapping. Both the
+/// lowerCamelCase and the original field names are accepted.
+pub fn json_to_message(
+    json: &JsonValue,
+    info: &MessageInfo,
+    schema: &ProtoSchema,
+) -> anyhow::Result<MessageValue> {
+    let JsonValue::Object(object) = json else {
+        bail!("Expected a JSON object for message {}", info.full_name);
+    };
+
+    let mut fields = Vec::new();
+
+    for (name, value) in object {
+        let field = info
+            .iter_fields()
+            .find(|field| field.name == *name || json_name(&field.name) == *name)
+            .with_context(|| format!("Unknown field {} in message {}", name, info.full_name))?;
+
+        if value.is_null() {
+            continue;
+        }
+
+        let values = match field.multiplicity {
+            Multiplicity::Repeated | Multiplicity::RepeatedPacked => match value {
+                JsonValue::Array(values) => values
+                    .iter()
+                    .map(|value| json_to_value(value, &field.field_type, schema))
+                    .collect::<anyhow::Result<Vec<Value>>>(),
+                JsonValue::Object(entries) => entries
+                    .iter()
+                    .map(|(key, value)| json_to_map_entry(key, value, field, schema))
+                    .collect::<anyhow::Result<Vec<Value>>>(),
+                _ => Err(anyhow!("Expected a JSON array")),
+            },
+            Multiplicity::Single | Multiplicity::Optional => {
+                json_to_value(value, &field.field_type, schema).map(|value| vec![value])
+            }
+        }
+        .with_context(|| format!("Invalid value for field {}.{}", info.full_name, name))?;
+
+        fields.extend(values.into_iter().map(|value| FieldValue {
+            number: field.number,
+            value,
+        }));
+    }
+
+    Ok(MessageValue {
+        msg_ref: info.self_ref,
+        garbage: None,
+        fields,
+    })
+}
+
+fn json_to_map_entry(
+    key: &str,
+    value: &JsonValue,
+    field: &MessageField,
+    schema: &ProtoSchema,
+) -> anyhow::Result<Value> {
+    let ValueType::Message(msg_ref) = field.field_type else {
+        bail!("Field {} is not a map", field.name);
+    };
+
+    let entry = schema.context.resolve_message(msg_ref);
+    let (Some(key_field), Some(value_field)) = (entry.get_field(1), entry.get_field(2)) else {
+        bail!("Field {} is not a map", field.name);
+    };
+
+    // Map keys are always strings in JSON, even when the key type is numeric or bool
+    let key = match key_field.field_type {
+        ValueType::String => JsonValue::String(key.to_string()),
+        _ => serde_json::from_str(key).with_context(|| format!("Invalid map key {}", key))?,
+    };
+
+    Ok(Value::Message(Box::new(MessageValue {
+        msg_ref,
+        garbage: None,
+        fields: vec![
+            FieldValue {
+                number: 1,
+                value: json_to_value(&key, &key_field.field_type, schema)?,
+            },
+            FieldValue {
+                number: 2,
+                value: json_to_value(value, &value_field.field_type, schema)?,
+            },
+        ],
+    })))
+}
+
+fn json_to_value(
+    json: &JsonValue,
+    value_type: &ValueType,
+    schema: &ProtoSchema,
+) -> anyhow::Result<Value> {
+    let value = match value_type {
+        ValueType::Double => Value::Double(json_to_float(json)?),
+        ValueType::Float => Value::Float(json_to_float(json)? as f32),
+        ValueType::Int32 => Value::Int32(json_to_integer(json)?),
+        ValueType::SInt32 => Value::SInt32(json_to_integer(json)?),
+        ValueType::SFixed32 => Value::SFixed32(json_to_integer(json)?),
+        ValueType::Int64 => Value::Int64(json_to_integer(json)?),
+        ValueType::SInt64 => Value::SInt64(json_to_integer(json)?),
+        ValueType::SFixed64 => Value::SFixed64(json_to_integer(json)?),
+        ValueType::UInt32 => Value::UInt32(json_to_integer(json)?),
+        ValueType::Fixed32 => Value::Fixed32(json_to_integer(json)?),
+        ValueType::UInt64 => Value::UInt64(json_to_integer(json)?),
+        ValueType::Fixed64 => Value::Fixed64(json_to_integer(json)?),
+        ValueType::Bool => Value::Bool(json.as_bool().context("Expected a boolean")?),
+        ValueType::String => Value::String(json.as_str().context("Expected a string")?.into()),
+        ValueType::Bytes => {
+            let encoded = json.as_str().context("Expected a base64 string")?;
+            Value::Bytes(Bytes::from(BASE64.decode(encoded)?))
+        }
+        ValueType::Message(msg_ref) => {
+            let info = schema.context.resolve_message(*msg_ref);
+            Value::Message(Box::new(json_to_message(json, info, schema)?))
+        }
+        ValueType::Enum(enum_ref) => {
+            let info = schema.context.resolve_enum(*enum_ref);
+
+            let value = match json {
+                JsonValue::String(name) => schema
+                    .enum_value(&info.full_name, name)
+                    .with_context(|| format!("Unknown value {} for enum {}", name, info.name))?,
+                json => json_to_integer(json)?,
+            };
+
+            Value::Enum(EnumValue {
+                enum_ref: *enum_ref,
+                value,
+            })
+        }
+    };
+
+    Ok(value)
+}
+
+fn json_to_float(json: &JsonValue) -> anyhow::Result<f64> {
+    match json {
+        JsonValue::Number(number) => number.as_f64().context("Expected a number"),
+        JsonValue::String(value) => match value.as_str() {
+            "NaN" => Ok(f64::NAN),
+            "Infinity" => Ok(f64::INFINITY),
+            "-Infinity" => Ok(f64::NEG_INFINITY),
+            value => Ok(value.parse()?),
+        },
+        _ => bail!("Expected a number"),
+    }
+}
+
+/// Integers may be given either as JSON numbers or as strings, which is how
+/// 64 bit integers are represented.
+fn json_to_integer<T>(json: &JsonValue) -> anyhow::Result<T>
+where
+    T: TryFrom<i64> + TryFrom<u64> + std::str::FromStr,
+{
+    let out_of_range = || anyhow!("Number {} out of range", json);
+
+    match json {
+        JsonValue::Number(number) => {
+            if let Some(value) = number.as_i64() {
+                T::try_from(value).map_err(|_| out_of_range())
+            } else if let Some(value) = number.as_u64() {
+                T::try_from(value).map_err(|_| out_of_range())
+            } else {
+                bail!("Expected an integer, found {}", number)
+            }
+        }
+        JsonValue::String(value) => value
+            .parse()
+            .map_err(|_| anyhow!("Expected an integer, found {:?}", value)),
+        _ => bail!("Expected an integer"),
+    }
+}
+
+/// Convert a field name to the lowerCamelCase name used by the proto3 JSON mapping.
+fn json_name(name: &str) -> String {
+    let mut json_name = String::with_capacity(name.len());
+    let mut capitalize_next = false;
+
+    for c in name.chars() {
+        if c == '_' {
+            capitalize_next = true;
+        } else if capitalize_next {
+            json_name.extend(c.to_uppercase());
+            capitalize_next = false;
+        } else {
+            json_name.push(c);
+        }
+    }
+
+    json_name
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wire_format_round_trip() {
+        for indexes in [vec![0], vec![1], vec![2, 0, 3]] {
+            let payload = write_wire_format(42, &indexes, b"message");
+            let (id, decoded, bytes) = split_wire_format(&payload).unwrap();
+
+            assert_eq!(id, 42);
+            assert_eq!(decoded, indexes);
+            assert_eq!(bytes, b"message");
+        }
+    }
+
+    #[test]
+    fn first_message_is_a_single_zero() {
+        assert_eq!(write_wire_format(1, &[0], b""), [0, 0, 0, 0, 1, 0]);
+    }
+
+    #[test]
+    fn invalid_index_count_is_rejected() {
+        // Negative count (zigzag 1 = -1)
+        assert!(split_wire_format(&[0, 0, 0, 0, 1, 1]).is_err());
+        // Count larger than the remaining bytes (zigzag 200 = 100)
+        assert!(split_wire_format(&[0, 0, 0, 0, 1, 0xc8, 0x01, 2]).is_err());
+        // Missing magic byte
+        assert!(split_wire_format(&[1, 0, 0, 0, 1, 0]).is_err());
+    }
+
+    #[test]
+    fn declared_messages_follow_nesting() {
+        let schema = r#"
+            syntax = "proto3";
+            package example.v1;
+
+            // message Commented { }
+            message First {
+                string name = 1;
+            }
+
+            message Second {
+                enum Kind { A = 0; }
+
+                message Inner {
+                    message Deepest {}
+                }
+
+                message Other {}
+                oneof choice { string a = 1; }
+            }
+        "#;
+
+        let messages = declared_messages(schema)
+            .into_iter()
+            .map(|message| (message.full_name, message.indexes))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            messages,
+            [
+                ("example.v1.First".to_string(), vec![0]),
+                ("example.v1.Second".to_string(), vec![1]),
+                ("example.v1.Second.Inner".to_string(), vec![1, 0]),
+                ("example.v1.Second.Inner.Deepest".to_string(), vec![1, 0, 0]),
+                ("example.v1.Second.Other".to_string(), vec![1, 1]),
+            ]
+        );
+    }
+
+    #[test]
+    fn enums_are_encoded_by_name_or_number() {
+        let schema = r#"
+            syntax = "proto3";
+            package example.v1;
+
+            message Order {
+                enum Status {
+                    option allow_alias = true;
+                    UNKNOWN = 0;
+                    PENDING = 1 [deprecated = true];
+                    SHIPPED = 0x2;
+                    CANCELLED = -1;
+                }
+
+                Status status = 1;
+            }
+        "#;
+
+        let schema = ProtoSchema::parse(schema, &[]).unwrap();
+        let encode = |json: JsonValue| schema.encode(None, &json).unwrap().1;
+
+        assert_eq!(encode(serde_json::json!({ "status": "PENDING" })), [8, 1]);
+        assert_eq!(encode(serde_json::json!({ "status": 1 })), [8, 1]);
+        assert_eq!(encode(serde_json::json!({ "status": "SHIPPED" })), [8, 2]);
+        assert_eq!(
+            encode(serde_json::json!({ "status": "CANCELLED" })),
+            encode(serde_json::json!({ "status": -1 }))
+        );
+        assert!(schema
+            .encode(None, &serde_json::json!({ "status": "DELIVERED" }))
+            .is_err());
+    }
+}
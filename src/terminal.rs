@@ -0,0 +1,40 @@
+use std::io::Write;
+
+/// Print rows as a table with left aligned columns.
+pub fn print_table<const N: usize>(header: [&str; N], rows: &[[String; N]]) {
+    let mut widths = header.map(str::len);
+
+    for row in rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.len());
+        }
+    }
+
+    let print_row = |cells: &[&str]| {
+        let line = cells
+            .iter()
+            .zip(widths)
+            .map(|(cell, width)| format!("{:<width$}", cell, width = width))
+            .collect::<Vec<String>>()
+            .join("  ");
+
+        println!("{}", line.trim_end());
+    };
+
+    print_row(&header);
+
+    for row in rows {
+        print_row(&row.iter().map(String::as_str).collect::<Vec<&str>>());
+    }
+}
+
+/// Ask for confirmation on the terminal, anything but yes is a no.
+pub fn confirm(prompt: &str) -> anyhow::Result<bool> {
+    eprint!("{} [y/N] ", prompt);
+    std::io::stderr().flush()?;
+
+    let mut answer = String::new();
+    std::io::stdin().read_line(&mut answer)?;
+
+    Ok(matches!(answer.trim(), "y" | "Y" | "yes" | "YES"))
+}